@@ -1,39 +1,612 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
 ///
 /// 簡単な計算文字列を解析し、計算した結果を取得します。
 ///
 pub fn calculation(target: &str) -> Result<String, String> {
-    // トークン単位に切り分け
-    let tokens = tokenize(target)?;
+    calculation_with_options(target, &Options::default())
+}
+
+///
+/// 設定を指定して簡単な計算文字列を解析し、計算した結果を取得します。
+///
+pub fn calculation_with_options(target: &str, options: &Options) -> Result<String, String> {
+    Calculator::with_options(options.clone()).calculation(target)
+}
+
+///
+/// 簡単な計算文字列を解析し、計算した結果を数値・真偽値として取得します。
+///
+pub fn evaluate(target: &str) -> Result<Answer, String> {
+    evaluate_with_options(target, &Options::default())
+}
+
+///
+/// 設定を指定して簡単な計算文字列を解析し、計算した結果を数値・真偽値として取得します。
+///
+pub fn evaluate_with_options(target: &str, options: &Options) -> Result<Answer, String> {
+    Calculator::with_options(options.clone()).evaluate(target)
+}
+
+///
+/// 変数を保持し、複数回の計算で共有する計算機です
+/// 「rate = 0.08」で代入した変数は、以降の計算で「price * (1 + rate)」のように使用できます
+///
+#[derive(Debug, Clone, Default)]
+pub struct Calculator {
+    options: Options,
+    variables: BTreeMap<String, Answer>,
+    /// f(x, y) = ... で定義した関数
+    functions: BTreeMap<String, Arc<Definition>>,
+    /// 呼び出し中の関数の引数
+    /// 末尾が現在計算している関数の引数となります
+    scopes: Vec<BTreeMap<String, Answer>>,
+}
+impl Calculator {
+    ///
+    /// 標準の設定で計算機を作成します
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// 設定を指定して計算機を作成します
+    ///
+    pub fn with_options(options: Options) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    ///
+    /// 計算時の設定を取得します
+    ///
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    ///
+    /// 計算文字列を解析し、計算した結果を取得します
+    /// 式中で代入した変数は計算機に保持されます
+    /// 「;」・改行で区切られた複数の文は先頭から順に計算し、最後の文の計算結果を返却します
+    ///
+    pub fn calculation(&mut self, target: &str) -> Result<String, String> {
+        Ok(self.evaluate(target)?.format(self.options.complex_format))
+    }
+
+    ///
+    /// 計算文字列を解析し、計算した結果を数値・真偽値として取得します
+    /// 式中で代入した変数は計算機に保持されます
+    /// 「;」・改行で区切られた複数の文は先頭から順に計算し、最後の文の計算結果を返却します
+    ///
+    pub fn evaluate(&mut self, target: &str) -> Result<Answer, String> {
+        // トークン単位に切り分け
+        let tokens = tokenize(target, &self.options)?;
+
+        // 切り分けたトークンを元に計算し、返却する
+        let result = parse_token(&tokens, self)?;
+
+        Ok(result)
+    }
+
+    ///
+    /// 「;」・改行で区切られた複数の文を先頭から順に計算し、全ての文の計算結果を取得します
+    ///
+    pub fn evaluate_all(&mut self, target: &str) -> Result<Vec<Answer>, String> {
+        // トークン単位に切り分け
+        let tokens = tokenize(target, &self.options)?;
+
+        // 切り分けたトークンを元に計算し、返却する
+        let results = parse_token_all(&tokens, self)?;
+
+        Ok(results)
+    }
+
+    ///
+    /// 変数に値を設定します
+    /// 定数と同じ名前を指定した場合、式中では変数の値が優先されます
+    ///
+    pub fn set_variable(&mut self, name: &str, value: impl Into<Answer>) {
+        self.variables.insert(name.to_string(), value.into());
+    }
+
+    ///
+    /// 変数の値を取得します
+    /// 変数が定義されていない場合は None を返却します
+    ///
+    pub fn get_variable(&self, name: &str) -> Option<&Answer> {
+        self.variables.get(name)
+    }
+
+    ///
+    /// 変数を削除し、削除した変数の値を返却します
+    ///
+    pub fn remove_variable(&mut self, name: &str) -> Option<Answer> {
+        self.variables.remove(name)
+    }
+
+    ///
+    /// 定義されている変数の一覧を取得します
+    ///
+    pub fn variables(&self) -> &BTreeMap<String, Answer> {
+        &self.variables
+    }
+
+    ///
+    /// 関数が定義されているか判定します
+    ///
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    ///
+    /// 定義した関数を削除します
+    /// 関数が定義されていた場合は true を返却します
+    ///
+    pub fn remove_function(&mut self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
+    ///
+    /// 名前に対応する変数・定数の値を取得します
+    /// 関数の計算中は引数を優先し、変数・定数・虚数単位のいずれでもない場合はエラーとします
+    ///
+    fn lookup(&self, name: &str) -> Result<Answer, String> {
+        if let Some(value) = self.scopes.last().and_then(|scope| scope.get(name)) {
+            return Ok(value.clone());
+        }
+        if let Some(value) = self.variables.get(name) {
+            return Ok(value.clone());
+        }
+        match self.options.constants.get(name) {
+            Some(value) => Ok(Answer::Number(*value)),
+            // 変数・定数として定義されていない「i」「j」は虚数単位とする
+            None if name == "i" || name == "j" => Ok(Answer::Complex(0.0, 1.0)),
+            None => Err(format!("定義されていない変数です。「{name}」")),
+        }
+    }
+}
+
+///
+/// 計算結果です
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// 数値
+    Number(f64),
+    /// 比較演算子・論理演算子による真偽値
+    Bool(bool),
+    /// f(x, y) = ... で定義した関数
+    Function(String),
+    /// [1, 2, 3] のリスト
+    List(Vec<Answer>),
+    /// 実部と虚部からなる複素数（3+4i）
+    /// 虚部が 0 の場合は数値とします
+    Complex(f64, f64),
+}
+impl Answer {
+    ///
+    /// 数値として取得します
+    /// 数値以外の場合はエラーとします
+    ///
+    pub fn as_number(&self) -> Result<f64, String> {
+        match self {
+            Answer::Number(value) => Ok(*value),
+            value => Err(value.unexpected("数値")),
+        }
+    }
+
+    ///
+    /// 真偽値として取得します
+    /// 真偽値以外の場合はエラーとします
+    ///
+    pub fn as_bool(&self) -> Result<bool, String> {
+        match self {
+            Answer::Bool(value) => Ok(*value),
+            value => Err(value.unexpected("真偽値")),
+        }
+    }
+
+    ///
+    /// リストとして取得します
+    /// リスト以外の場合はエラーとします
+    ///
+    pub fn as_list(&self) -> Result<&[Answer], String> {
+        match self {
+            Answer::List(values) => Ok(values),
+            value => Err(value.unexpected("リスト")),
+        }
+    }
+
+    ///
+    /// 値の種類の名前を取得します
+    ///
+    fn kind(&self) -> &'static str {
+        match self {
+            Answer::Number(_) => "数値",
+            Answer::Bool(_) => "真偽値",
+            Answer::Function(_) => "関数",
+            Answer::List(_) => "リスト",
+            Answer::Complex(..) => "複素数",
+        }
+    }
+
+    ///
+    /// 複素数の表示形式を指定して文字列に変換します
+    ///
+    pub fn format(&self, style: ComplexFormat) -> String {
+        match self {
+            Answer::Number(value) => value.to_string(),
+            Answer::Bool(value) => value.to_string(),
+            Answer::Function(value) => value.clone(),
+            Answer::List(values) => {
+                let values = values.iter()
+                    .map(|value| value.format(style))
+                    .collect::<Vec<String>>();
+                format!("[{}]", values.join(", "))
+            },
+            Answer::Complex(re, im) => match style {
+                ComplexFormat::Rectangular if *re == 0.0 => format!("{im}i"),
+                ComplexFormat::Rectangular if im.is_sign_negative() => format!("{re}-{}i", -im),
+                ComplexFormat::Rectangular => format!("{re}+{im}i"),
+                ComplexFormat::Polar => format!("{}∠{}", re.hypot(*im), im.atan2(*re)),
+            },
+        }
+    }
+
+    ///
+    /// 期待した種類の値ではなかった場合のエラーメッセージを取得します
+    ///
+    fn unexpected(&self, expect: &str) -> String {
+        format!("{expect}を期待していましたが、{}が出現しました。「{self}」", self.kind())
+    }
+}
+impl From<f64> for Answer {
+    fn from(value: f64) -> Self {
+        Answer::Number(value)
+    }
+}
+impl From<bool> for Answer {
+    fn from(value: bool) -> Self {
+        Answer::Bool(value)
+    }
+}
+impl From<Vec<f64>> for Answer {
+    fn from(values: Vec<f64>) -> Self {
+        Answer::List(values.into_iter().map(Answer::Number).collect())
+    }
+}
+impl std::fmt::Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format(ComplexFormat::Rectangular))
+    }
+}
+
+///
+/// 計算時の設定です
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// 「%」「//」で負数を扱う場合の丸め方
+    pub division: Division,
+    /// 全角数字・全角記号・「×」「÷」等を半角の数字・演算子に変換してから解析するか
+    pub normalize: bool,
+    /// 2(3 + 4) のような演算子を省略した乗算の扱い
+    pub implicit_multiplication: ImplicitMultiplication,
+    /// 整数以外の階乗「2.5!」をガンマ関数で計算するか
+    /// false の場合、整数以外の階乗はエラーとします
+    pub gamma_factorial: bool,
+    /// 「%」の扱い
+    pub percent_sign: PercentSign,
+    /// 「//」の扱い
+    pub double_slash: DoubleSlash,
+    /// 計算結果を文字列で取得する場合の複素数の表示形式
+    pub complex_format: ComplexFormat,
+    /// 式中で使用できる定数の一覧
+    /// 標準の定数は default_constants で取得でき、任意の定数を追加できます
+    pub constants: BTreeMap<String, f64>,
+    /// 定義した関数を呼び出す際の入れ子の上限
+    /// 再帰呼び出しがこの回数を超えた場合はエラーとします
+    pub max_call_depth: usize,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            division: Division::default(),
+            normalize: true,
+            implicit_multiplication: ImplicitMultiplication::default(),
+            gamma_factorial: false,
+            percent_sign: PercentSign::default(),
+            double_slash: DoubleSlash::default(),
+            complex_format: ComplexFormat::default(),
+            constants: default_constants(),
+            max_call_depth: 100,
+        }
+    }
+}
+
+///
+/// 標準で定義されている定数の一覧を取得します
+///
+pub fn default_constants() -> BTreeMap<String, f64> {
+    [
+        ("pi", std::f64::consts::PI),
+        ("π", std::f64::consts::PI),
+        ("e", std::f64::consts::E),
+        ("tau", std::f64::consts::TAU),
+        ("τ", std::f64::consts::TAU),
+        ("phi", 1.618_033_988_749_895),
+        ("φ", 1.618_033_988_749_895),
+        ("inf", f64::INFINITY),
+        ("nan", f64::NAN),
+    ]
+    .into_iter()
+    .map(|(name, value)| (String::from(name), value))
+    .collect()
+}
 
-    // 優先順位の調整
-    let tokens = adjust_brackets(&tokens);
+///
+/// 剰余「%」・整数除算「//」の丸め方です
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Division {
+    /// 負の無限大方向に丸めます
+    /// 剰余は除数と同じ符号になります（-7 // 2 = -4, -7 % 2 = 1）
+    #[default]
+    Floored,
+    /// 0方向に丸めます
+    /// 剰余は被除数と同じ符号になります（-7 // 2 = -3, -7 % 2 = -1）
+    Truncated,
+}
+
+///
+/// 演算子を省略した乗算の扱いです
+/// 値の直後に開始かっこが続く場合「*」が省略されているものとして扱います
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImplicitMultiplication {
+    /// 省略を許可せず、エラーとします
+    #[default]
+    Disabled,
+    /// 「*」「/」と同じ優先順位で左から計算します（1 / 2(3) = 1.5）
+    SamePrecedence,
+    /// 「*」「/」より先に計算します（1 / 2(3) = 0.16666666666666666）
+    HigherPrecedence,
+}
+
+///
+/// 「%」の扱いです
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PercentSign {
+    /// 剰余の演算子として扱います（7 % 3 = 1）
+    #[default]
+    Modulo,
+    /// 電卓と同じ百分率の後置演算子として扱います
+    /// 50% = 0.5, 200 + 10% = 220, 200 - 10% = 180, 200 * 10% = 20
+    Percent,
+}
 
-    // 切り分けたトークンを元に計算し、返却する
-    let result = parse_token(&tokens)?;
+///
+/// 「//」の扱いです
+/// 「#」は常に行末までのコメントとして扱います
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DoubleSlash {
+    /// 整数除算の演算子として扱います（7 // 2 = 3）
+    #[default]
+    FloorDivide,
+    /// 行末までのコメントとして扱います（base * 1.1 // 10% margin）
+    Comment,
+}
 
-    Ok(result)
+///
+/// 複素数の表示形式です
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComplexFormat {
+    /// 実部と虚部で表示します（3+4i）
+    #[default]
+    Rectangular,
+    /// 絶対値と偏角（ラジアン）で表示します（5∠0.9272952180016122）
+    Polar,
 }
 
 ///
 /// 文字列をトークン単位に切り分けます
 ///
-fn tokenize(target: &str) -> Result<Vec<Token>, String> {
+fn tokenize(target: &str, options: &Options) -> Result<Vec<Token>, String> {
     let mut tokens = Vec::new();
 
-    let chars = target.chars().collect::<Vec<char>>();
+    let chars = target.chars()
+        .map(|c| if options.normalize { normalize_char(c) } else { c })
+        .collect::<Vec<char>>();
     let mut index = 0;
     while index < chars.len() {
         match chars.get(index).unwrap() {
-            ' ' => {
+            ' ' | '\t' | '\r' => {
                 index += 1;
                 continue;
             },
-            '(' => tokens.push(Token::Brackets(Brackets::Start)),
-            ')' => tokens.push(Token::Brackets(Brackets::End)),
+            // 「;」・改行は文の区切りとして扱う
+            ';' | '\n' => tokens.push(Token::Separator),
+            '#' => {
+                index = skip_comment(&chars, index);
+                continue;
+            },
+            // かっこは種類と出現位置を保持する
+            '(' => tokens.push(Token::Brackets(Brackets::Start(BracketKind::Round, index))),
+            ')' => tokens.push(Token::Brackets(Brackets::End(BracketKind::Round, index))),
+            '[' => tokens.push(Token::Brackets(Brackets::Start(BracketKind::Square, index))),
+            ']' => tokens.push(Token::Brackets(Brackets::End(BracketKind::Square, index))),
+            '{' => tokens.push(Token::Brackets(Brackets::Start(BracketKind::Curly, index))),
+            '}' => tokens.push(Token::Brackets(Brackets::End(BracketKind::Curly, index))),
             '+' => tokens.push(Token::Operator(Operator::Plus)),
             '-' => tokens.push(Token::Operator(Operator::Minus)),
-            '*' => tokens.push(Token::Operator(Operator::Multiply)),
-            '/' => tokens.push(Token::Operator(Operator::Divide)),
+            // 「**」は「^」と同じべき乗の演算子として扱う
+            '*' => {
+                if chars.get(index + 1) == Some(&'*') {
+                    tokens.push(Token::Operator(Operator::Power));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::Multiply));
+                }
+            },
+            '^' => tokens.push(Token::Operator(Operator::Power)),
+            // 値の後以外の「!」は否定の演算子として扱う（!x, !!x）
+            // 「!!」は二重階乗、「!=」は比較の演算子として扱う
+            // 「!==」は階乗の後に「==」が続くものとして扱う（3!==6）
+            '!' => {
+                if !is_value_end(tokens.last()) {
+                    tokens.push(Token::Operator(Operator::PrefixNot));
+                } else if chars.get(index + 1) == Some(&'=') && chars.get(index + 2) == Some(&'=') {
+                    tokens.push(Token::Operator(Operator::Factorial));
+                } else if chars.get(index + 1) == Some(&'=') {
+                    tokens.push(Token::Operator(Operator::NotEqual));
+                    index += 1;
+                } else if chars.get(index + 1) == Some(&'!') {
+                    tokens.push(Token::Operator(Operator::DoubleFactorial));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::Factorial));
+                }
+            },
+            // 「//」は設定により整数除算の演算子、またはコメントとして扱う
+            '/' => {
+                if chars.get(index + 1) == Some(&'/') {
+                    if options.double_slash == DoubleSlash::Comment {
+                        index = skip_comment(&chars, index);
+                        continue;
+                    }
+                    tokens.push(Token::Operator(Operator::FloorDivide));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::Divide));
+                }
+            },
+            // 「==」は比較の演算子、「=」は変数への代入として扱う
+            '=' => {
+                if chars.get(index + 1) == Some(&'=') {
+                    tokens.push(Token::Operator(Operator::Equal));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Assign);
+                }
+            },
+            // 「<<」「>>」はビットシフトの演算子として扱う
+            '<' => {
+                if chars.get(index + 1) == Some(&'=') {
+                    tokens.push(Token::Operator(Operator::LessEqual));
+                    index += 1;
+                } else if chars.get(index + 1) == Some(&'<') {
+                    tokens.push(Token::Operator(Operator::ShiftLeft));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::Less));
+                }
+            },
+            '>' => {
+                if chars.get(index + 1) == Some(&'=') {
+                    tokens.push(Token::Operator(Operator::GreaterEqual));
+                    index += 1;
+                } else if chars.get(index + 1) == Some(&'>') {
+                    tokens.push(Token::Operator(Operator::ShiftRight));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::Greater));
+                }
+            },
+            // 「&&」「||」は論理演算子、「&」「|」はビット演算子として扱う
+            '&' => {
+                if chars.get(index + 1) == Some(&'&') {
+                    tokens.push(Token::Operator(Operator::And));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::BitAnd));
+                }
+            },
+            '|' => {
+                if chars.get(index + 1) == Some(&'|') {
+                    tokens.push(Token::Operator(Operator::Or));
+                    index += 1;
+                } else {
+                    tokens.push(Token::Operator(Operator::BitOr));
+                }
+            },
+            '~' => tokens.push(Token::Operator(Operator::BitNot)),
+            '@' => tokens.push(Token::Operator(Operator::MatrixMultiply)),
+            // 英字・ギリシャ文字が連続する場合は1つの単語として結合し、
+            // キーワード以外は定数等の名前として扱う
+            c if is_identifier_start(*c) => {
+                let mut word = String::new();
+                while let Some(c) = chars.get(index)
+                    .filter(|c| is_identifier_start(**c) || c.is_ascii_digit()) {
+                    word.push(*c);
+                    index += 1;
+                }
+
+                match word.as_str() {
+                    "and" => tokens.push(Token::Operator(Operator::And)),
+                    "or" => tokens.push(Token::Operator(Operator::Or)),
+                    "not" => tokens.push(Token::Operator(Operator::Not)),
+                    "xor" => tokens.push(Token::Operator(Operator::BitXor)),
+                    "if" => tokens.push(Token::Branch(Branch::If)),
+                    "then" => tokens.push(Token::Branch(Branch::Then)),
+                    "else" => tokens.push(Token::Branch(Branch::Else)),
+                    _ => tokens.push(Token::Identifier(word)),
+                }
+                continue;
+            },
+            ',' => tokens.push(Token::Comma),
+            '?' => tokens.push(Token::Branch(Branch::Question)),
+            ':' => tokens.push(Token::Branch(Branch::Colon)),
+            '%' => tokens.push(Token::Operator(
+                match options.percent_sign {
+                    PercentSign::Modulo => Operator::Modulo,
+                    PercentSign::Percent => Operator::Percent,
+                }
+            )),
+            // 0x, 0b, 0o で始まる場合は16進数、2進数、8進数の整数として結合し、
+            // 10進数に変換する
+            // 数字の間には区切り文字「_」を使用できる
+            '0' if matches!(
+                chars.get(index + 1), Some('x' | 'X' | 'b' | 'B' | 'o' | 'O')
+            ) => {
+                let (radix, name) = match chars.get(index + 1).unwrap() {
+                    'x' | 'X' => (16, "16進数"),
+                    'b' | 'B' => (2, "2進数"),
+                    _ => (8, "8進数"),
+                };
+                let mut literal = chars[index..index + 2].iter().collect::<String>();
+                let mut digits = String::new();
+
+                index += 2;
+                while let Some(c) = chars.get(index)
+                    .filter(|c| c.is_alphanumeric() || **c == '_') {
+                    literal.push(*c);
+                    if *c != '_' {
+                        if !c.is_digit(radix) {
+                            return Err(
+                                format!("{name}に使用できない文字が出現しました。「{literal}」")
+                            );
+                        }
+                        digits.push(*c);
+                    }
+                    index += 1;
+                }
+
+                if digits.is_empty() {
+                    return Err(format!("{name}の数字がありません。「{literal}」"));
+                }
+                let num = u64::from_str_radix(&digits, radix)
+                    .map_err(|_| format!("{name}の値が大きすぎます。「{literal}」"))?;
+
+                tokens.push(Token::Value(num.to_string()));
+                continue;
+            },
             // 連続する数字は1つのトークンとして結合
             // 「.」が連続して出現した場合はエラーとする
             '0' | '1' | '2' | '3' | '4' |
@@ -61,6 +634,41 @@ fn tokenize(target: &str) -> Result<Vec<Token>, String> {
                     index += 1;
                 }
 
+                // 「e」「E」が続く場合は指数表記として指数部を結合する
+                // 指数部は符号を付けることができ、数字が1つ以上必要
+                if let Some('e' | 'E') = chars.get(index) {
+                    num.push('e');
+                    index += 1;
+
+                    if let Some(sign @ ('+' | '-')) = chars.get(index) {
+                        num.push(*sign);
+                        index += 1;
+                    }
+
+                    let mut digits = 0;
+                    while let Some(digit) = chars.get(index).filter(|c| c.is_ascii_digit()) {
+                        num.push(*digit);
+                        digits += 1;
+                        index += 1;
+                    }
+
+                    if digits == 0 {
+                        return Err(format!("指数部の数字がありません。「{num}」"));
+                    }
+                    if let Some('.') = chars.get(index) {
+                        return Err(format!("指数部に「.」は使用できません。「{num}.」"));
+                    }
+                }
+
+                // 直後に「i」「j」が続く場合は虚数として扱う（4i, 2.5j）
+                if let Some('i' | 'j') = chars.get(index)
+                    && !chars.get(index + 1)
+                        .is_some_and(|c| is_identifier_start(*c) || c.is_ascii_digit()) {
+                    tokens.push(Token::Imaginary(num));
+                    index += 1;
+                    continue;
+                }
+
                 tokens.push(Token::Value(num));
                 continue;
             },
@@ -75,238 +683,587 @@ fn tokenize(target: &str) -> Result<Vec<Token>, String> {
 }
 
 ///
-/// 2 * 3 - 4 / 5 → (2 * 3) - (4 / 5)
-/// のように優先順位が上の演算子の開始・終了にかっこを付けます
+/// 行末までのコメントを読み飛ばし、改行の位置を取得します
+/// 読み飛ばした文字も位置に数えるため、以降のかっこの位置は入力文字列上の位置のままとなります
 ///
-fn adjust_brackets(target: &[Token]) -> Vec<Token> {
-    let mut result = Vec::new();
-    let mut brackets_flag = false;
+fn skip_comment(chars: &[char], index: usize) -> usize {
+    chars[index..].iter()
+        .position(|c| *c == '\n')
+        .map_or(chars.len(), |position| index + position)
+}
 
-    let mut index = 0;
-    for item in target.iter() {
-        match item {
-            Token::Value(value) => {
-                result.push(Token::Value(value.to_string()));
-                // 開始かっこが追加されていた場合、終了かっこも追加する
-                if brackets_flag {
-                    result.push(Token::Brackets(Brackets::End));
-                    index += 1;
-                    brackets_flag = false;
-                }
-            },
-            Token::Brackets(value) => {
-                // かっこが出現した場合は追加かっこは削除する
-                if brackets_flag {
-                    result.remove(index - 3);
-                    index -= 1;
-                    brackets_flag = false;
-                }
-                result.push(Token::Brackets(
-                    match value {
-                        Brackets::Start => Brackets::Start,
-                        Brackets::End => Brackets::End
-                    }
-                ));
-            },
-            Token::Operator(value) => {
-                match value {
-                    Operator::Plus => {
-                        result.push(Token::Operator(Operator::Plus));
-                    },
-                    Operator::Minus => {
-                        result.push(Token::Operator(Operator::Minus));
-                    },
-                    Operator::Multiply => {
-                        match result.get(index - 1).unwrap() {
-                            Token::Value(_) => {
-                                result.insert(index - 1, Token::Brackets(Brackets::Start));
-                                result.push(Token::Operator(Operator::Multiply));
-                                index += 1;
-                                brackets_flag = true;
-                            },
-                            _ => result.push(Token::Operator(Operator::Multiply)),
-                        }
-                    },
-                    Operator::Divide => {
-                        match result.get(index - 1).unwrap() {
-                            Token::Value(_) => {
-                                result.insert(index - 1, Token::Brackets(Brackets::Start));
-                                result.push(Token::Operator(Operator::Divide));
-                                index += 1;
-                                brackets_flag = true;
-                            },
-                            _ => result.push(Token::Operator(Operator::Divide)),
-                        }
-                    },
-                }
-            }
-        }
-        index += 1;
-    }
+///
+/// 数値・名前・終了かっこ・後置演算子のように、値の終わりとなるトークンか判定します
+///
+fn is_value_end(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(Token::Value(_) | Token::Imaginary(_) | Token::Identifier(_) |
+            Token::Brackets(Brackets::End(..)) |
+            Token::Operator(Operator::Factorial | Operator::DoubleFactorial | Operator::Percent))
+    )
+}
 
-    result
+///
+/// 名前の先頭に使用できる文字か判定します
+/// 英字、「_」、π 等のギリシャ文字が使用できます
+///
+fn is_identifier_start(target: char) -> bool {
+    target.is_ascii_alphabetic() || target == '_' ||
+        ('\u{0370}'..='\u{03FF}').contains(&target)
+}
+
+///
+/// 日本語入力で入力される全角数字・記号を半角の文字に変換します
+///
+fn normalize_char(target: char) -> char {
+    match target {
+        // 全角の英数字・記号（「！」から「～」まで）は対応する半角文字に変換する
+        '！'..='～' => char::from_u32(target as u32 - 0xFEE0).unwrap_or(target),
+        '×' | '・' => '*',
+        '÷' => '/',
+        '−' => '-',
+        '　' => ' ',
+        _ => target,
+    }
 }
 
 ///
 /// トークンのリストを解析し、計算結果を取得します
 ///
-fn parse_token(target: &[Token]) -> Result<String, String> {
-    let mut stack = Vec::new();
+fn parse_token(target: &[Token], calculator: &mut Calculator) -> Result<Answer, String> {
+    let mut results = parse_token_all(target, calculator)?;
+    Ok(results.pop().unwrap())
+}
 
-    let mut index = 0;
-    while index < target.len() {
-        // 左辺の取得
-        // スタックにブロックが存在していた場合、それを左辺にする
-        let lhs = if stack.is_empty() {
-            match target.get(index).ok_or("左辺の取得に失敗しました。")? {
-                Token::Value(value) => {
-                    index += 1;
-                    Value::Val(value.to_string())
-                },
-                // かっこが出現した場合、かっこ内を先に処理する
-                Token::Brackets(value) => {
-                    match value {
-                        Brackets::Start => {
-                            let (val, i) = parse_inner_brackets(target, index)?;
-                            index = i;
-                            val
-                        },
-                        _ => return Err(String::from("想定外の終了かっこが出現しました。")),
-                    }
-                },
-                _ => {
-                    let message= String::from(
-                        "数値を期待していましたが、数値以外が出現しました。"
-                    );
-                    return Err(message)
-                },
+///
+/// 「;」・改行で区切られた複数の文を含むトークンのリストを解析し、
+/// 全ての文の計算結果を取得します
+///
+fn parse_token_all(target: &[Token], calculator: &mut Calculator) -> Result<Vec<Answer>, String> {
+    // 演算子の優先順位に従い、トークンを木構造に組み立てる
+    // 途中の文で解析に失敗した場合、どの文も計算しない
+    let statements = Parser::new(target, &calculator.options).parse_statements()?;
+
+    // 先頭の文から順に計算する
+    statements.iter()
+        .map(|value| value.execute(calculator))
+        .collect()
+}
+
+///
+/// トークンのリストを演算子の優先順位・結合性に従って木構造に組み立てます
+///
+struct Parser<'a> {
+    tokens: &'a [Token],
+    index: usize,
+    options: &'a Options,
+}
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token], options: &'a Options) -> Self {
+        Self {
+            tokens,
+            index: 0,
+            options,
+        }
+    }
+
+    ///
+    /// 現在位置のトークンを取得します
+    ///
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.index)
+    }
+
+    ///
+    /// 「;」・改行で区切られた文を全て組み立てます
+    /// 空の文は読み飛ばし、文が1つもない場合はエラーとします
+    ///
+    fn parse_statements(&mut self) -> Result<Vec<Value>, String> {
+        let mut statements = Vec::new();
+
+        loop {
+            while let Some(Token::Separator) = self.peek() {
+                self.index += 1;
+            }
+            if self.peek().is_none() {
+                break;
             }
-        } else {
-            Value::Block(Box::new(stack.pop().unwrap()))
-        };
 
-        // 演算子の取得
-        let operator = match target.get(index).ok_or("演算子の取得に失敗しました。")? {
-            Token::Operator(value) => Value::Op(match value {
-                Operator::Plus => Operator::Plus,
-                Operator::Minus => Operator::Minus,
-                Operator::Multiply => Operator::Multiply,
-                Operator::Divide => Operator::Divide,
-            }),
-            _ => return Err(
-                String::from("演算子を期待していましたが、演算子以外が出現しました。")
-            ),
-        };
-        index += 1;
+            statements.push(self.parse_statement()?);
 
-        // 右辺の取得
-        // 右辺の前が+, -演算子であった場合、数値に+, -を付与する
-        let rhs = match target.get(index).ok_or("右辺の取得に失敗しました。")? {
-            Token::Value(value) => Value::Val(value.to_string()),
-            Token::Operator(value) => match value {
-                Operator::Plus => {
-                    index += 1;
-                    let val = target.get(index).ok_or("右辺の取得に失敗しました。")?;
-                    Value::Val(match val {
-                        Token::Value(val2) => {
-                            val2.to_string()
-                        }
-                        _ => return Err(String::from("右辺の取得に失敗しました。")),
-                    })
+            // 文の途中で解析が終了した場合はエラーとする
+            match self.peek() {
+                None | Some(Token::Separator) => {},
+                Some(Token::Brackets(Brackets::End(..))) => {
+                    return Err(String::from("想定外の終了かっこが出現しました。"));
                 },
-                Operator::Minus => {
-                    index += 1;
-                    let val = target.get(index).ok_or("右辺の取得に失敗しました。")?;
-                    Value::Val(match val {
-                        Token::Value(val2) => {
-                            format!("-{val2}")
-                        }
-                        _ => return Err(String::from("右辺の取得に失敗しました。")),
-                    })
+                Some(Token::Assign) => {
+                    return Err(String::from(
+                        "「=」の左辺には変数名、または関数名と引数名を指定してください。"
+                    ));
                 },
-                _ => return Err(String::from("右辺の取得に失敗しました。")),
-            },
-            // かっこが出現した場合、かっこ内を先に処理する
-            Token::Brackets(value) => {
-                match value {
-                    Brackets::Start => {
-                        let (val, i) = parse_inner_brackets(target, index)?;
-                        index = i - 1;
-                        val
-                    },
-                    _ => return Err(String::from("想定外の終了かっこが出現しました。")),
-                }
-            },
-        };
-
-        stack.push(Block::new(lhs, rhs, operator));
+                Some(_) => return Err(
+                    String::from("演算子を期待していましたが、演算子以外が出現しました。")
+                ),
+            }
+        }
 
-        index += 1;
+        if statements.is_empty() {
+            return Err(String::from("数値の取得に失敗しました。"));
+        }
+        Ok(statements)
     }
 
+    ///
+    /// 変数への代入、または式を組み立てます
+    /// a = b = 1 のように連続した代入は右から順に代入します
+    ///
+    fn parse_statement(&mut self) -> Result<Value, String> {
+        // 関数の定義は引数名を読み込み済みの状態で本体を組み立てる
+        if let Some((name, parameters)) = self.parse_definition_head() {
+            if let Some(duplicate) = parameters.iter().enumerate()
+                .find(|(index, parameter)| parameters[..*index].contains(parameter))
+                .map(|(_, parameter)| parameter) {
+                return Err(format!("関数「{name}」の引数名が重複しています。「{duplicate}」"));
+            }
 
-    let value = stack.pop().unwrap().execute()?;
-    Ok(value)
-}
+            let body = self.parse_expression(0)?;
+            return Ok(Value::Definition(Box::new(Definition::new(&name, parameters, body))));
+        }
 
-///
-/// 通常かっこ、多重かっこの計算処理を行います
-///
-fn parse_inner_brackets(target: &[Token], index: usize) -> Result<(Value, usize), String> {
-    let mut expression = Vec::new();
-    let mut brackets = Vec::new();
+        if let Some(Token::Identifier(name)) = self.peek()
+            && let Some(Token::Assign) = self.tokens.get(self.index + 1) {
+            self.index += 2;
+            let value = self.parse_statement()?;
+            return Ok(Value::Assignment(Box::new(Assignment::new(name, value))));
+        }
+
+        self.parse_expression(0)
+    }
+
+    ///
+    /// 現在位置が f(x, y) = のような関数定義の場合、関数名と引数名を取得して読み進めます
+    /// 関数定義でない場合は読み進めずに None を返却します
+    ///
+    fn parse_definition_head(&mut self) -> Option<(String, Vec<String>)> {
+        let Some(Token::Identifier(name)) = self.peek() else {
+            return None;
+        };
+        let Some(Token::Brackets(Brackets::Start(BracketKind::Round, _))) =
+            self.tokens.get(self.index + 1) else {
+            return None;
+        };
+
+        // 「,」で区切られた引数名を取得する
+        let mut index = self.index + 2;
+        let mut parameters = Vec::new();
+        if !matches!(self.tokens.get(index), Some(Token::Brackets(Brackets::End(..)))) {
+            loop {
+                let Some(Token::Identifier(parameter)) = self.tokens.get(index) else {
+                    return None;
+                };
+                parameters.push(parameter.to_string());
+                index += 1;
+
+                match self.tokens.get(index) {
+                    Some(Token::Comma) => index += 1,
+                    _ => break,
+                }
+            }
+        }
+
+        let Some(Token::Brackets(Brackets::End(BracketKind::Round, _))) =
+            self.tokens.get(index) else {
+            return None;
+        };
+        let Some(Token::Assign) = self.tokens.get(index + 1) else {
+            return None;
+        };
+
+        self.index = index + 2;
+        Some((name.to_string(), parameters))
+    }
+
+    ///
+    /// 優先順位が min_precedence 以上の演算子を結合し、式を組み立てます
+    ///
+    fn parse_expression(&mut self, min_precedence: u8) -> Result<Value, String> {
+        // 左辺の取得
+        let mut lhs = self.parse_operand()?;
+
+        loop {
+            // 「[」が続く場合、左辺のリストの要素を取得する添字として扱う
+            // 演算子の省略が許可されている場合、数値・かっこの後の「[」は乗算として扱う（2[3 + 4]）
+            // 変数・関数の呼び出しの後の「[」は、値が数値であっても常に添字として扱う（xs[0]）
+            if let Some(Token::Brackets(Brackets::Start(BracketKind::Square, _))) = self.peek()
+                && (self.options.implicit_multiplication == ImplicitMultiplication::Disabled
+                    || lhs.is_indexable()) {
+                if INDEX_PRECEDENCE < min_precedence {
+                    break;
+                }
+                let (start, start_position) = self.parse_start_brackets()?;
+                let index = self.parse_expression(0)?;
+                self.parse_end_brackets(start, start_position)?;
+
+                lhs = Value::Index(Box::new(Index::new(lhs, index)));
+                continue;
+            }
+
+            // 「?」が出現した場合、左辺を条件とする条件式を組み立てる
+            if let Some(Token::Branch(Branch::Question)) = self.peek() {
+                if CONDITIONAL_PRECEDENCE < min_precedence {
+                    break;
+                }
+                self.index += 1;
+                lhs = self.parse_ternary(lhs)?;
+                continue;
+            }
+
+            // 後置演算子が出現した場合、左辺に演算子を適用する
+            if let Some(Token::Operator(operator)) = self.peek()
+                && let Some(precedence) = operator.postfix_precedence() {
+                if precedence < min_precedence {
+                    break;
+                }
+                self.index += 1;
+                lhs = Value::Unary(Box::new(
+                    Unary::new(lhs, Value::Op(operator.clone()))
+                ));
+                continue;
+            }
+
+            // 演算子以外が出現した場合は呼び出し元で判断する
+            let Some((operator, precedence, associativity)) = self.peek_operator() else {
+                break;
+            };
+
+            // 優先順位が低い演算子は呼び出し元で結合する
+            if precedence < min_precedence {
+                break;
+            }
+            // 省略された演算子の場合はトークンを読み進めない
+            if let Some(Token::Operator(_)) = self.peek() {
+                self.index += 1;
+            }
+
+            // 連続した比較演算子は1つの比較式として組み立てる
+            if associativity == Associativity::Chain {
+                lhs = self.parse_comparison(lhs, operator, precedence)?;
+                continue;
+            }
+
+            // 右辺の取得
+            // 左結合の場合は同じ優先順位の演算子を右辺に含めない
+            let next_precedence = match associativity {
+                Associativity::Right => precedence,
+                _ => precedence + 1,
+            };
+            let rhs = self.parse_expression(next_precedence)?;
+
+            lhs = Value::Block(Box::new(
+                Block::new(lhs, rhs, Value::Op(operator))
+            ));
+        }
+
+        Ok(lhs)
+    }
+
+    ///
+    /// cond ? a : b の条件式を組み立てます
+    /// 「?」は読み込み済みの状態で呼び出します
+    ///
+    fn parse_ternary(&mut self, condition: Value) -> Result<Value, String> {
+        let then_value = self.parse_expression(0)?;
+        self.expect_branch(Branch::Colon)?;
+
+        // 「:」以降の条件式は右結合とする（a ? b : c ? d : e → a ? b : (c ? d : e)）
+        let else_value = self.parse_expression(CONDITIONAL_PRECEDENCE)?;
+
+        Ok(Value::Conditional(Box::new(
+            Conditional::new(condition, then_value, else_value)
+        )))
+    }
+
+    ///
+    /// if cond then a else b の条件式を組み立てます
+    ///
+    fn parse_if(&mut self) -> Result<Value, String> {
+        self.expect_branch(Branch::If)?;
+        let condition = self.parse_expression(0)?;
+
+        self.expect_branch(Branch::Then)?;
+        let then_value = self.parse_expression(0)?;
+
+        self.expect_branch(Branch::Else)?;
+        let else_value = self.parse_expression(0)?;
+
+        Ok(Value::Conditional(Box::new(
+            Conditional::new(condition, then_value, else_value)
+        )))
+    }
+
+    ///
+    /// 現在位置が指定した条件式のキーワードであることを確認し、読み進めます
+    ///
+    fn expect_branch(&mut self, branch: Branch) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Branch(value)) if *value == branch => {
+                self.index += 1;
+                Ok(())
+            },
+            _ => Err(format!(
+                "「{0}」を期待していましたが、「{0}」以外が出現しました。", branch.text()
+            )),
+        }
+    }
+
+    ///
+    /// 0 <= x < 10 のように連続した比較演算子を1つの比較式に組み立てます
+    /// 最初の比較演算子は読み込み済みの状態で呼び出します
+    ///
+    fn parse_comparison(
+        &mut self, lhs: Value, operator: Operator, precedence: u8
+    ) -> Result<Value, String> {
+        let mut comparison = Comparison::new(lhs);
+        let mut operator = operator;
+
+        loop {
+            let rhs = self.parse_expression(precedence + 1)?;
+            comparison.push(Value::Op(operator), rhs);
+
+            // 同じ優先順位の比較演算子が続く場合は比較式に追加する
+            match self.peek_operator() {
+                Some((next, next_precedence, Associativity::Chain))
+                    if next_precedence == precedence => {
+                    operator = next;
+                    self.index += 1;
+                },
+                _ => break,
+            }
+        }
 
-    let mut index = index + 1;
-    while index < target.len() {
-        match target.get(index).ok_or("値の取得に失敗しました。")? {
+        Ok(Value::Comparison(Box::new(comparison)))
+    }
+
+    ///
+    /// 現在位置の二項演算子と、その優先順位・結合性を取得します
+    /// 演算子の省略が許可されている場合、開始かっこ・名前の前では「*」を補います
+    ///
+    fn peek_operator(&self) -> Option<(Operator, u8, Associativity)> {
+        match self.peek()? {
+            Token::Operator(operator) => {
+                let (precedence, associativity) = operator.precedence()?;
+                Some((operator.clone(), precedence, associativity))
+            },
+            Token::Brackets(Brackets::Start(..)) | Token::Identifier(_) => {
+                match self.options.implicit_multiplication {
+                    ImplicitMultiplication::Disabled => None,
+                    ImplicitMultiplication::SamePrecedence => {
+                        let (precedence, associativity) = Operator::Multiply.precedence()?;
+                        Some((Operator::Multiply, precedence, associativity))
+                    },
+                    ImplicitMultiplication::HigherPrecedence => Some(
+                        (Operator::Multiply, IMPLICIT_MULTIPLY_PRECEDENCE, Associativity::Left)
+                    ),
+                }
+            },
+            _ => None,
+        }
+    }
+
+    ///
+    /// 演算子の左辺・右辺となる数値、かっこ内の式を取得します
+    ///
+    fn parse_operand(&mut self) -> Result<Value, String> {
+        match self.peek().ok_or("数値の取得に失敗しました。")? {
             Token::Value(value) => {
-                expression.push(Token::Value(value.to_string()));
+                self.index += 1;
+                Ok(Value::Val(value.to_string()))
             },
-            Token::Operator(value) => {
-                expression.push(Token::Operator(
-                    match value {
-                        Operator::Plus => Operator::Plus,
-                        Operator::Minus => Operator::Minus,
-                        Operator::Multiply => Operator::Multiply,
-                        Operator::Divide => Operator::Divide,
-                    }
-                ));
+            Token::Imaginary(value) => {
+                self.index += 1;
+                Ok(Value::Imaginary(value.to_string()))
+            },
+            // かっこが出現した場合、かっこ内を先に組み立てる
+            Token::Brackets(Brackets::Start(BracketKind::Square, _)) => self.parse_list(),
+            Token::Brackets(Brackets::Start(..)) => self.parse_inner_brackets(),
+            Token::Brackets(Brackets::End(..)) => {
+                Err(String::from("想定外の終了かっこが出現しました。"))
             },
-            // 多重かっこだった場合の処理
-            Token::Brackets(value) => {
-                match value {
-                    Brackets::Start => {
-                        brackets.push(Brackets::Start);
-                        expression.push(
-                            Token::Brackets(Brackets::Start)
-                        );
+            // 名前の直後に「(」が続く場合は関数呼び出しとして扱う
+            Token::Identifier(name) => {
+                self.index += 1;
+                match self.peek() {
+                    Some(Token::Brackets(Brackets::Start(BracketKind::Round, _))) => {
+                        self.parse_call(name)
                     },
-                    Brackets::End => {
-                        if brackets.is_empty() {
-                            index += 1;
-                            break;
-                        } else {
-                            brackets.pop().unwrap();
-                            expression.push(
-                                Token::Brackets(Brackets::End)
-                            );
-                        }
-                    }
+                    _ => Ok(Value::Ident(name.to_string())),
                 }
             },
+            Token::Branch(Branch::If) => self.parse_if(),
+            Token::Branch(_) | Token::Comma | Token::Assign | Token::Separator => {
+                Err(String::from("数値を期待していましたが、数値以外が出現しました。"))
+            },
+            // 単項演算子が出現した場合、続く数値・かっこ等に演算子を適用する
+            Token::Operator(operator) => {
+                let precedence = operator.unary_precedence().ok_or(
+                    "数値を期待していましたが、数値以外が出現しました。"
+                )?;
+                self.index += 1;
+
+                let value = self.parse_expression(precedence)?;
+                Ok(Value::Unary(Box::new(
+                    Unary::new(value, Value::Op(operator.clone()))
+                )))
+            },
         }
-        index += 1;
     }
 
-    let value = Value::Val(parse_token(&expression)?);
-    Ok((value, index))
+    ///
+    /// 通常かっこ、多重かっこ内の式を組み立てます
+    ///
+    fn parse_inner_brackets(&mut self) -> Result<Value, String> {
+        let (start, start_position) = self.parse_start_brackets()?;
+
+        let value = self.parse_expression(0)?;
+
+        self.parse_end_brackets(start, start_position)?;
+        Ok(value)
+    }
+
+    ///
+    /// [1, 2, 3] のリスト、または「[」「]」で囲まれた式を組み立てます
+    /// 「,」を含まず、[1 + 2] のように演算子を含む式を囲む場合はかっことして扱います
+    /// [5], [x], [[1, 2]] のように1つの値のみを囲む場合は要素が1つのリストとして扱います
+    ///
+    fn parse_list(&mut self) -> Result<Value, String> {
+        let (start, start_position) = self.parse_start_brackets()?;
+
+        let mut elements = Vec::new();
+        if !matches!(self.peek(), Some(Token::Brackets(Brackets::End(..)))) {
+            let value = self.parse_expression(0)?;
+            if !value.is_single_value() && !matches!(self.peek(), Some(Token::Comma)) {
+                self.parse_end_brackets(start, start_position)?;
+                return Ok(value);
+            }
+            elements.push(value);
+
+            // 「,」で区切られた要素を取得する
+            // 末尾の「,」は無視する
+            while let Some(Token::Comma) = self.peek() {
+                self.index += 1;
+                if matches!(self.peek(), Some(Token::Brackets(Brackets::End(..)))) {
+                    break;
+                }
+                elements.push(self.parse_expression(0)?);
+            }
+        }
+
+        self.parse_end_brackets(start, start_position)?;
+        Ok(Value::List(elements))
+    }
+
+    ///
+    /// sqrt(2), log(8, 2) のような関数呼び出しを組み立てます
+    /// 関数名は読み込み済みの状態で呼び出します
+    ///
+    fn parse_call(&mut self, name: &str) -> Result<Value, String> {
+        let (start, start_position) = self.parse_start_brackets()?;
+
+        // 「,」で区切られた引数を取得する
+        let mut arguments = Vec::new();
+        if !matches!(self.peek(), Some(Token::Brackets(Brackets::End(..)))) {
+            loop {
+                arguments.push(self.parse_expression(0)?);
+                match self.peek() {
+                    Some(Token::Comma) => self.index += 1,
+                    _ => break,
+                }
+            }
+        }
+
+        self.parse_end_brackets(start, start_position)?;
+        Ok(Value::Call(Box::new(Call::new(name, arguments))))
+    }
+
+    ///
+    /// 開始かっこの種類と位置を取得し、読み進めます
+    ///
+    fn parse_start_brackets(&mut self) -> Result<(BracketKind, usize), String> {
+        match self.peek() {
+            Some(Token::Brackets(Brackets::Start(kind, position))) => {
+                self.index += 1;
+                Ok((*kind, *position))
+            },
+            _ => Err(String::from("開始かっこの取得に失敗しました。")),
+        }
+    }
+
+    ///
+    /// 開始かっこに対応する終了かっこを読み進めます
+    /// 開始かっこと終了かっこの種類が異なる場合はエラーとします
+    ///
+    fn parse_end_brackets(
+        &mut self, start: BracketKind, start_position: usize
+    ) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Brackets(Brackets::End(end, end_position))) => {
+                if start != *end {
+                    return Err(format!(
+                        "かっこの種類が一致しません。「{}」（{}文字目）と「{}」（{}文字目）",
+                        start.start_char(), start_position + 1,
+                        end.end_char(), end_position + 1
+                    ));
+                }
+                self.index += 1;
+                Ok(())
+            },
+            None => Err(String::from("終了かっこが不足しています。")),
+            Some(_) => Err(
+                String::from("演算子を期待していましたが、演算子以外が出現しました。")
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Token {
     Value(String),
+    Imaginary(String),
     Operator(Operator),
     Brackets(Brackets),
+    Branch(Branch),
+    Identifier(String),
+    Comma,
+    Assign,
+    Separator,
+}
+
+///
+/// 条件式 if cond then a else b, cond ? a : b を構成するキーワードです
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Branch {
+    If,
+    Then,
+    Else,
+    Question,
+    Colon,
+}
+impl Branch {
+    ///
+    /// 入力文字列上の表記を取得します
+    ///
+    fn text(&self) -> &'static str {
+        match self {
+            Branch::If => "if",
+            Branch::Then => "then",
+            Branch::Else => "else",
+            Branch::Question => "?",
+            Branch::Colon => ":",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -315,19 +1272,232 @@ enum Operator {
     Minus,
     Multiply,
     Divide,
+    FloorDivide,
+    Modulo,
+    Power,
+    Factorial,
+    DoubleFactorial,
+    Percent,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    Not,
+    PrefixNot,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    MatrixMultiply,
+}
+impl Operator {
+    ///
+    /// 二項演算子として使用した場合の優先順位と結合性を取得します
+    /// 優先順位は数値が大きいほど先に計算されます
+    /// 二項演算子として使用できない場合は None を返却します
+    ///
+    fn precedence(&self) -> Option<(u8, Associativity)> {
+        match self {
+            Operator::Or => Some((2, Associativity::Left)),
+            Operator::And => Some((3, Associativity::Left)),
+            Operator::Equal | Operator::NotEqual |
+            Operator::Less | Operator::LessEqual |
+            Operator::Greater | Operator::GreaterEqual => Some((5, Associativity::Chain)),
+            Operator::BitOr => Some((6, Associativity::Left)),
+            Operator::BitXor => Some((7, Associativity::Left)),
+            Operator::BitAnd => Some((8, Associativity::Left)),
+            Operator::ShiftLeft | Operator::ShiftRight => Some((9, Associativity::Left)),
+            Operator::Plus | Operator::Minus => Some((10, Associativity::Left)),
+            Operator::Multiply | Operator::Divide |
+            Operator::FloorDivide | Operator::Modulo |
+            Operator::MatrixMultiply => Some((20, Associativity::Left)),
+            Operator::Power => Some((40, Associativity::Right)),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 単項演算子として使用した場合の優先順位を取得します
+    /// 単項演算子として使用できない場合は None を返却します
+    ///
+    fn unary_precedence(&self) -> Option<u8> {
+        match self {
+            Operator::Not => Some(4),
+            // 前置の「!」は「not」と異なり、符号と同じ優先順位で続く値のみに適用する
+            Operator::Plus | Operator::Minus |
+            Operator::PrefixNot | Operator::BitNot => Some(SIGN_PRECEDENCE),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 後置演算子として使用した場合の優先順位を取得します
+    /// 後置演算子として使用できない場合は None を返却します
+    ///
+    fn postfix_precedence(&self) -> Option<u8> {
+        match self {
+            Operator::Factorial | Operator::DoubleFactorial | Operator::Percent => Some(50),
+            _ => None,
+        }
+    }
+}
+
+///
+/// 条件式 cond ? a : b の優先順位です
+///
+const CONDITIONAL_PRECEDENCE: u8 = 1;
+
+///
+/// 符号「+」「-」と否定「!」「~」を前置した場合の優先順位です
+///
+const SIGN_PRECEDENCE: u8 = 30;
+
+///
+/// 演算子を省略した乗算を「*」「/」より先に計算する場合の優先順位です
+///
+const IMPLICIT_MULTIPLY_PRECEDENCE: u8 = 25;
+
+///
+/// リストの添字 xs[0] の優先順位です
+///
+const INDEX_PRECEDENCE: u8 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+    /// 0 <= x < 10 のように連続した演算子を1つの式として扱う
+    Chain,
 }
 
+///
+/// かっこの種類と、入力文字列中の出現位置を保持します
+///
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Brackets {
-    Start,
-    End,
+    Start(BracketKind, usize),
+    End(BracketKind, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BracketKind {
+    /// ( )
+    Round,
+    /// [ ]
+    Square,
+    /// { }
+    Curly,
+}
+impl BracketKind {
+    ///
+    /// 開始かっこの文字を取得します
+    ///
+    fn start_char(&self) -> char {
+        match self {
+            BracketKind::Round => '(',
+            BracketKind::Square => '[',
+            BracketKind::Curly => '{',
+        }
+    }
+
+    ///
+    /// 終了かっこの文字を取得します
+    ///
+    fn end_char(&self) -> char {
+        match self {
+            BracketKind::Round => ')',
+            BracketKind::Square => ']',
+            BracketKind::Curly => '}',
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Value {
     Val(String),
+    Imaginary(String),
+    Ident(String),
     Op(Operator),
     Block(Box<Block>),
+    Unary(Box<Unary>),
+    Comparison(Box<Comparison>),
+    Conditional(Box<Conditional>),
+    Call(Box<Call>),
+    Assignment(Box<Assignment>),
+    Definition(Box<Definition>),
+    List(Vec<Value>),
+    Index(Box<Index>),
+}
+impl Value {
+    ///
+    /// 10%, -10% のように百分率の値か判定します
+    /// 符号が付いている場合は符号を除いて判定します
+    ///
+    fn is_percent(&self) -> bool {
+        match self {
+            Value::Unary(value) => match &value.operator {
+                Value::Op(Operator::Percent) => true,
+                Value::Op(Operator::Plus | Operator::Minus) => value.value.is_percent(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    ///
+    /// 数値、変数、関数の呼び出し、リストのように演算子を含まない値か判定します
+    /// 符号が付いている場合は符号を除いて判定します
+    ///
+    fn is_single_value(&self) -> bool {
+        match self {
+            Value::Unary(value) => {
+                matches!(value.operator, Value::Op(Operator::Plus | Operator::Minus))
+                    && value.value.is_single_value()
+            },
+            Value::Val(_) | Value::Imaginary(_) | Value::Ident(_) |
+            Value::Call(_) | Value::List(_) | Value::Index(_) => true,
+            _ => false,
+        }
+    }
+
+    ///
+    /// 変数、関数の呼び出し、リストのように計算結果がリストになり得る値か判定します
+    ///
+    fn is_indexable(&self) -> bool {
+        matches!(self, Value::Ident(_) | Value::Call(_) | Value::List(_) | Value::Index(_))
+    }
+
+    ///
+    /// 数値、またはブロックを計算した結果を取得します
+    ///
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        match self {
+            Value::Val(value) => Ok(Answer::Number(to_number(value)?)),
+            Value::Imaginary(value) => Ok(complex(0.0, to_number(value)?)),
+            Value::Ident(name) => calculator.lookup(name),
+            Value::Block(value) => value.execute(calculator),
+            Value::Unary(value) => value.execute(calculator),
+            Value::Comparison(value) => value.execute(calculator),
+            Value::Conditional(value) => value.execute(calculator),
+            Value::Call(value) => value.execute(calculator),
+            Value::Assignment(value) => value.execute(calculator),
+            Value::Definition(value) => value.execute(calculator),
+            Value::List(values) => values.iter()
+                .map(|value| value.execute(calculator))
+                .collect::<Result<Vec<Answer>, String>>()
+                .map(Answer::List),
+            Value::Index(value) => value.execute(calculator),
+            Value::Op(_) => Err(
+                String::from("数値を期待していましたが、演算子が出現しました。")
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -345,528 +1515,3423 @@ impl Block {
         }
     }
 
-    fn execute(&self) -> Result<String, String> {
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        // 論理演算子の場合は右辺を後から計算する
+        if let Value::Op(Operator::And | Operator::Or) = &self.operator {
+            return self.execute_logical(calculator);
+        }
+
         // 左辺の計算
         let lhs = match &self.lhs {
-            Value::Val(value) => {
-                value.to_string()
-            },
-            Value::Block(value) => {
-                value.execute()?
-            },
-            _ => return Err(String::from("左辺に演算子が出現しました。")),
+            Value::Op(_) => return Err(String::from("左辺に演算子が出現しました。")),
+            value => value.execute(calculator)?,
         };
-        let lhs = lhs.parse::<f64>().unwrap();
 
         // 右辺の計算
+        // 「+」「-」の右辺が百分率の場合、左辺に対する割合として計算する
         let rhs = match &self.rhs {
-            Value::Val(value) => {
-                value.to_string()
-            },
-            Value::Block(value) => {
-                value.execute()?
+            Value::Op(_) => return Err(String::from("右辺に演算子が出現しました。")),
+            value if value.is_percent() &&
+                matches!(self.operator, Value::Op(Operator::Plus | Operator::Minus)) => {
+                let rate = value.execute(calculator)?;
+                apply_binary(&Operator::Multiply, &lhs, &rate, &calculator.options)?
             },
-            _ => return Err(String::from("右辺に演算子が出現しました。")),
+            value => value.execute(calculator)?,
         };
-        let rhs = rhs.parse::<f64>().unwrap();
 
         // 演算子ごとに計算し、その結果を返却
         match &self.operator {
-            Value::Op(value) => {
-                Ok(match value {
-                    Operator::Plus => (lhs + rhs).to_string(),
-                    Operator::Minus => (lhs - rhs).to_string(),
-                    Operator::Multiply => (lhs * rhs).to_string(),
-                    Operator::Divide => (lhs / rhs).to_string(),
-                })
-            },
+            Value::Op(operator) => apply_binary(operator, &lhs, &rhs, &calculator.options),
             _ => Err(String::from("演算子を想定していましたが、演算子以外が出現しました。")),
         }
     }
-}
 
+    ///
+    /// 論理演算子「and」「or」を計算します
+    /// 左辺のみで結果が確定する場合、右辺は計算しません
+    ///
+    fn execute_logical(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        // 左辺の計算
+        let lhs = match &self.lhs {
+            Value::Op(_) => return Err(String::from("左辺に演算子が出現しました。")),
+            value => value.execute(calculator)?.as_bool()?,
+        };
 
+        // 「and」の左辺が false、「or」の左辺が true の場合は右辺を計算しない
+        match (&self.operator, lhs) {
+            (Value::Op(Operator::And), false) => return Ok(Answer::Bool(false)),
+            (Value::Op(Operator::Or), true) => return Ok(Answer::Bool(true)),
+            _ => {},
+        }
 
+        // 右辺の計算
+        match &self.rhs {
+            Value::Op(_) => Err(String::from("右辺に演算子が出現しました。")),
+            value => Ok(Answer::Bool(value.execute(calculator)?.as_bool()?)),
+        }
+    }
+}
 
-//----- TEST CODE --------------------------------------------------------------
+///
+/// -5, -(2 + 3), 3! のように1つの値に演算子を適用するブロックです
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Unary {
+    value: Value,
+    operator: Value,
+}
+impl Unary {
+    fn new(value: Value, operator: Value) -> Self {
+        Self {
+            value,
+            operator,
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::Block;
-    use crate::Operator;
-    use super::*;
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        // 値の計算
+        let value = match &self.value {
+            Value::Op(_) => return Err(String::from("値に演算子が出現しました。")),
+            value => value.execute(calculator)?,
+        };
 
-    //----- tokenize test ------------------------------------------------------
-    #[test]
-    fn tokenize_test1() {
-        let expect = vec![
-            Token::Value(String::from("1")),
-            Token::Operator(Operator::Plus),
-            Token::Value(String::from("2")),
-            Token::Operator(Operator::Minus),
-            Token::Value(String::from("3")),
-        ];
+        // 否定演算子の場合は真偽値として計算する
+        if let Value::Op(Operator::Not | Operator::PrefixNot) = &self.operator {
+            return Ok(Answer::Bool(!value.as_bool()?));
+        }
 
-        let formula = String::from("1+2 - 3");
-        let tokens = tokenize(&formula).unwrap();
+        // 符号は複素数にも適用する
+        if let Value::Op(operator @ (Operator::Plus | Operator::Minus)) = &self.operator {
+            return map_complex(&value, &|re, im| match operator {
+                Operator::Minus => Ok(complex(-re, -im)),
+                _ => Ok(complex(re, im)),
+            });
+        }
 
-        assert_eq!(expect, tokens);
+        // 演算子ごとに計算し、その結果を返却
+        // リストの場合は要素ごとに計算する
+        let options = &calculator.options;
+        map_numbers(&value, &|value| match &self.operator {
+            Value::Op(Operator::Plus) => Ok(value),
+            Value::Op(Operator::Minus) => Ok(-value),
+            Value::Op(Operator::Factorial) => factorial(value, options),
+            Value::Op(Operator::DoubleFactorial) => double_factorial(value),
+            Value::Op(Operator::Percent) => Ok(value / 100.0),
+            Value::Op(Operator::BitNot) => Ok(!to_integer(value)? as f64),
+            _ => Err(
+                String::from("単項演算子を想定していましたが、単項演算子以外が出現しました。")
+            ),
+        })
     }
+}
 
-    #[test]
-    fn tokenize_test2() {
-        let expect = vec![
-            Token::Value(String::from("100")),
-            Token::Operator(Operator::Multiply),
-            Token::Value(String::from("20")),
-            Token::Operator(Operator::Divide),
-            Token::Value(String::from("1505")),
-        ];
-
-        let formula = String::from("100 *20 /1505");
-        let tokens = tokenize(&formula).unwrap();
+///
+/// 1 < 2, 0 <= x < 10 のように値を比較し、真偽値を返却するブロックです
+/// 連続した比較は隣り合う値同士を比較し、全て成立する場合に true となります
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparison {
+    value: Value,
+    comparisons: Vec<(Value, Value)>,
+}
+impl Comparison {
+    fn new(value: Value) -> Self {
+        Self {
+            value,
+            comparisons: Vec::new(),
+        }
+    }
 
-        assert_eq!(expect, tokens);
+    ///
+    /// 比較演算子と右辺を追加します
+    ///
+    fn push(&mut self, operator: Value, rhs: Value) {
+        self.comparisons.push((operator, rhs));
     }
 
-    #[test]
-    fn tokenize_test3() {
-        let expect = vec![
-            Token::Value(String::from("12.34")),
-            Token::Operator(Operator::Minus),
-            Token::Value(String::from("5.678")),
-        ];
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        let mut lhs = self.value.execute(calculator)?;
 
-        let formula = String::from("12.34-5.678");
-        let tokens = tokenize(&formula).unwrap();
+        // 比較が成立しなかった時点で残りの値は計算しない
+        for (operator, rhs) in self.comparisons.iter() {
+            let rhs = rhs.execute(calculator)?;
+            if !compare(&lhs, &rhs, operator)? {
+                return Ok(Answer::Bool(false));
+            }
+            lhs = rhs;
+        }
 
-        assert_eq!(expect, tokens);
+        Ok(Answer::Bool(true))
     }
+}
 
-    #[test]
-    fn tokenize_test4() {
-        let expect = vec![
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("2")),
-            Token::Operator(Operator::Multiply),
-            Token::Value(String::from("2")),
-            Token::Brackets(Brackets::End),
-            Token::Operator(Operator::Plus),
-            Token::Value(String::from("30")),
-        ];
-
-        let formula = String::from("(2 * 2) + 30");
-        let tokens = tokenize(&formula).unwrap();
-
-        assert_eq!(expect, tokens);
+///
+/// if cond then a else b, cond ? a : b のように条件によって値を選択するブロックです
+/// 選択されなかった値は計算しません
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Conditional {
+    condition: Value,
+    then_value: Value,
+    else_value: Value,
+}
+impl Conditional {
+    fn new(condition: Value, then_value: Value, else_value: Value) -> Self {
+        Self {
+            condition,
+            then_value,
+            else_value,
+        }
     }
 
-    #[test]
-    fn tokenize_err_test1() {
-        let formula = String::from("23.5 + 10..45");
-        let tokens = tokenize(&formula);
-
-        match tokens {
-            Ok(_) => {panic!("エラーが発生しませんでした。")},
-            Err(value) => assert_eq!("「.」が連続して出現しました。", value),
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        if self.condition.execute(calculator)?.as_bool()? {
+            self.then_value.execute(calculator)
+        } else {
+            self.else_value.execute(calculator)
         }
     }
+}
 
-    #[test]
-    fn tokenize_err_test2() {
-        let formula = String::from("1 + あ");
-        let tokens = tokenize(&formula);
-
-        match tokens {
-            Ok(_) => {panic!("エラーが発生しませんでした。")},
-            Err(value) => {
-                assert_eq!("予期せぬ文字が出現しました。「あ」", value)
-            },
+///
+/// xs[0] のようにリストの要素を取得するブロックです
+/// 添字は先頭を 0 とします
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Index {
+    value: Value,
+    index: Value,
+}
+impl Index {
+    fn new(value: Value, index: Value) -> Self {
+        Self {
+            value,
+            index,
         }
     }
 
-    //----- parse_token test ---------------------------------------------------
-    #[test]
-    fn parse_token_test1() {
-        let formula = String::from("1 + 1");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        let value = self.value.execute(calculator)?;
+        let values = value.as_list()?;
+        let index = self.index.execute(calculator)?.as_number()?;
 
-        assert_eq!(String::from("2"), result.unwrap());
+        if index.fract() != 0.0 {
+            return Err(format!("添字には整数を指定してください。「{index}」"));
+        }
+        if index < 0.0 || index >= values.len() as f64 {
+            return Err(format!("添字が範囲外です。「{index}」（要素数{}個）", values.len()));
+        }
+        Ok(values[index as usize].clone())
     }
+}
 
-    #[test]
-    fn parse_token_test2() {
-        let formula = String::from("5 - 2 + 10");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
-
-        assert_eq!(String::from("13"), result.unwrap());
+///
+/// sqrt(2), log(8, 2) のように関数を呼び出すブロックです
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Call {
+    name: String,
+    arguments: Vec<Value>,
+}
+impl Call {
+    fn new(name: &str, arguments: Vec<Value>) -> Self {
+        Self {
+            name: name.to_string(),
+            arguments,
+        }
     }
 
-    #[test]
-    fn parse_token_test3() {
-        let formula = String::from("10 + 5 + 3 - 2");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        let arguments = self.arguments.iter()
+            .map(|value| value.execute(calculator))
+            .collect::<Result<Vec<Answer>, String>>()?;
 
-        assert_eq!(String::from("16"), result.unwrap());
+        match calculator.functions.get(&self.name) {
+            Some(definition) => Arc::clone(definition).call(&arguments, calculator),
+            None => call_function(&self.name, &arguments),
+        }
     }
+}
 
-    #[test]
-    fn parse_token_test4() {
-        let formula = String::from("10 - -2");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
-
-        assert_eq!(String::from("12"), result.unwrap());
+///
+/// f(x, y) = x^2 + y^2 のように関数を定義するブロックです
+/// 定義した関数は計算機に保持され、以降の計算で f(3, 4) のように呼び出せます
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    name: String,
+    parameters: Vec<String>,
+    body: Value,
+}
+impl Definition {
+    fn new(name: &str, parameters: Vec<String>, body: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            parameters,
+            body,
+        }
     }
 
-    #[test]
-    fn parse_token_test5() {
-        let formula = String::from("10.5 + -2.2");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+    ///
+    /// 関数を定義し、定義した関数の名前と引数を計算結果とします
+    ///
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        // 組み込み関数と同じ名前の関数は定義できない
+        if is_builtin_function(&self.name) {
+            return Err(format!("組み込み関数は定義し直すことはできません。「{}」", self.name));
+        }
 
-        assert_eq!(String::from("8.3"), result.unwrap());
+        calculator.functions.insert(self.name.clone(), Arc::new(self.clone()));
+        Ok(Answer::Function(format!("{}({})", self.name, self.parameters.join(", "))))
     }
 
-    #[test]
-    fn parse_token_test6() {
-        let formula = String::from("(2 + 2) * (3 + 3)");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+    ///
+    /// 引数を設定し、関数の本体を計算します
+    /// 引数は同じ名前の変数・定数より優先されます
+    ///
+    fn call(&self, arguments: &[Answer], calculator: &mut Calculator) -> Result<Answer, String> {
+        check_arity(&self.name, arguments, self.parameters.len())?;
+        if calculator.scopes.len() >= calculator.options.max_call_depth {
+            return Err(format!(
+                "関数「{}」の呼び出しが上限の{}回を超えました。",
+                self.name, calculator.options.max_call_depth
+            ));
+        }
 
-        assert_eq!(String::from("24"), result.unwrap());
-    }
+        let scope = self.parameters.iter().cloned()
+            .zip(arguments.iter().cloned())
+            .collect();
 
-    #[test]
-    fn parse_token_test7() {
-        let formula = String::from("5 * (4 + 4)");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+        // 計算に失敗した場合も引数を破棄する
+        calculator.scopes.push(scope);
+        let result = self.body.execute(calculator);
+        calculator.scopes.pop();
 
-        assert_eq!(String::from("40"), result.unwrap());
+        result
     }
+}
 
-    #[test]
-    fn parse_token_test8() {
-        let formula = String::from("(6 - 2) / 2");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
-
-        assert_eq!(String::from("2"), result.unwrap());
+///
+/// rate = 0.08 のように値を計算し、変数に代入するブロックです
+/// 代入した値をそのまま計算結果とします
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Assignment {
+    name: String,
+    value: Value,
+}
+impl Assignment {
+    fn new(name: &str, value: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+        }
     }
 
-    #[test]
-    fn parse_token_test9() {
-        let formula = String::from("((2 + 2) * (5 + 5)) / 2");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+    fn execute(&self, calculator: &mut Calculator) -> Result<Answer, String> {
+        // 定数と同じ名前の変数は定義できない
+        if calculator.options.constants.contains_key(&self.name) {
+            return Err(format!("定数に代入することはできません。「{}」", self.name));
+        }
 
-        assert_eq!(String::from("20"), result.unwrap());
+        let value = self.value.execute(calculator)?;
+        calculator.variables.insert(self.name.clone(), value.clone());
+        Ok(value)
     }
+}
 
-    #[test]
-    fn parse_token_test10() {
-        let formula = String::from("3 * (((5 + 5) * 2) + 10) / 2");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+///
+/// 二項演算子を計算します
+/// リスト同士の場合は要素ごとに計算し、一方のみリストの場合は全ての要素に他方の値を適用します
+/// 「@」は行列の積として計算します
+///
+fn apply_binary(
+    operator: &Operator, lhs: &Answer, rhs: &Answer, options: &Options
+) -> Result<Answer, String> {
+    if *operator == Operator::MatrixMultiply {
+        return matrix_multiply(lhs, rhs);
+    }
 
-        assert_eq!(String::from("45"), result.unwrap());
+    match (lhs, rhs) {
+        (Answer::List(lhs_values), Answer::List(rhs_values)) => {
+            if !is_same_length(lhs, rhs) {
+                return Err(format!(
+                    "リストの大きさが一致しません。（{} と {}）", shape(lhs), shape(rhs)
+                ));
+            }
+            lhs_values.iter().zip(rhs_values)
+                .map(|(lhs, rhs)| apply_binary(operator, lhs, rhs, options))
+                .collect::<Result<Vec<Answer>, String>>()
+                .map(Answer::List)
+        },
+        (Answer::List(lhs), rhs) => lhs.iter()
+            .map(|lhs| apply_binary(operator, lhs, rhs, options))
+            .collect::<Result<Vec<Answer>, String>>()
+            .map(Answer::List),
+        (lhs, Answer::List(rhs)) => rhs.iter()
+            .map(|rhs| apply_binary(operator, lhs, rhs, options))
+            .collect::<Result<Vec<Answer>, String>>()
+            .map(Answer::List),
+        (Answer::Complex(..), _) | (_, Answer::Complex(..)) => {
+            calculate_complex(operator, to_complex(lhs)?, to_complex(rhs)?)
+        },
+        (lhs, rhs) => Ok(Answer::Number(
+            calculate(operator, lhs.as_number()?, rhs.as_number()?, options)?
+        )),
     }
+}
 
-    #[test]
-    fn parse_token_test11() {
-        let formula = String::from("10 * (((1 + 1) / 2) - 9)");
-        let tokens = tokenize(&formula);
-        let result = parse_token(&tokens.unwrap());
+///
+/// 数値同士に二項演算子を適用します
+/// 「/」「^」は0での除算を inf とし、「//」「%」は0での除算をエラーとします
+///
+fn calculate(operator: &Operator, lhs: f64, rhs: f64, options: &Options) -> Result<f64, String> {
+    Ok(match operator {
+        Operator::Plus => lhs + rhs,
+        Operator::Minus => lhs - rhs,
+        Operator::Multiply => lhs * rhs,
+        Operator::Divide => lhs / rhs,
+        Operator::FloorDivide => {
+            if rhs == 0.0 {
+                return Err(String::from("0で除算することはできません。"));
+            }
+            match options.division {
+                Division::Floored => (lhs / rhs).floor(),
+                Division::Truncated => (lhs / rhs).trunc(),
+            }
+        },
+        Operator::Modulo => {
+            if rhs == 0.0 {
+                return Err(String::from("0で除算することはできません。"));
+            }
+            // 切り捨て方向の剰余の符号が除数と異なる場合、除数を加算する
+            let remainder = lhs % rhs;
+            match options.division {
+                Division::Floored if remainder != 0.0 &&
+                    (remainder < 0.0) != (rhs < 0.0) => {
+                    remainder + rhs
+                },
+                _ => remainder,
+            }
+        },
+        Operator::Power => lhs.powf(rhs),
+        Operator::BitAnd => (to_integer(lhs)? & to_integer(rhs)?) as f64,
+        Operator::BitOr => (to_integer(lhs)? | to_integer(rhs)?) as f64,
+        Operator::BitXor => (to_integer(lhs)? ^ to_integer(rhs)?) as f64,
+        Operator::ShiftLeft => {
+            (to_integer(lhs)? << to_shift_amount(rhs)?) as f64
+        },
+        Operator::ShiftRight => {
+            (to_integer(lhs)? >> to_shift_amount(rhs)?) as f64
+        },
+        _ => return Err(
+            String::from("二項演算子を想定していましたが、二項演算子以外が出現しました。")
+        ),
+    })
+}
 
-        assert_eq!(String::from("-80"), result.unwrap());
-    }
+///
+/// 複素数同士に二項演算子を適用します
+/// 四則演算とべき乗のみ計算でき、0での除算は実数と同じく実部・虚部をそれぞれ0で除算した値とします
+/// 0のべき乗は指数の実部が正の場合 0、負の場合 inf、0 の場合 NaN とします（0 ^ 0 = 1）
+///
+fn calculate_complex(
+    operator: &Operator, (a, b): (f64, f64), (c, d): (f64, f64)
+) -> Result<Answer, String> {
+    match operator {
+        Operator::Plus => Ok(complex(a + c, b + d)),
+        Operator::Minus => Ok(complex(a - c, b - d)),
+        Operator::Multiply => Ok(complex(a * c - b * d, a * d + b * c)),
+        Operator::Divide => {
+            let denominator = c * c + d * d;
+            if denominator == 0.0 {
+                // 0 の実部・虚部は 0 のままとし、0 / 0 のみ NaN とする（i / 0 = infi）
+                let divide = |value: f64| if value == 0.0 { 0.0 } else { value / c };
+                return Ok(complex(divide(a), divide(b)));
+            }
+            Ok(complex((a * c + b * d) / denominator, (b * c - a * d) / denominator))
+        },
+        Operator::Power => {
+            if a == 0.0 && b == 0.0 {
+                return Ok(Answer::Number(match (c, d) {
+                    (0.0, 0.0) => 1.0,
+                    (c, _) if c > 0.0 => 0.0,
+                    (c, _) if c < 0.0 => f64::INFINITY,
+                    _ => f64::NAN,
+                }));
+            }
 
-    //---- adjust_brackets test-------------------------------------------------
-    #[test]
-    fn adjust_brackets_test1() {
-        let tokens = tokenize("2 * 2 + 1").unwrap();
-        let adjust_tokens = adjust_brackets(&tokens);
+            // 整数乗は乗算を繰り返して計算し、i^2 = -1 のように誤差を生じないようにする
+            if d == 0.0 && c.fract() == 0.0 && c.abs() <= 64.0 {
+                let mut result = Answer::Number(1.0);
+                for _ in 0..c.abs() as u32 {
+                    result = calculate_complex(&Operator::Multiply, to_complex(&result)?, (a, b))?;
+                }
+                if c < 0.0 {
+                    result = calculate_complex(
+                        &Operator::Divide, (1.0, 0.0), to_complex(&result)?
+                    )?;
+                }
+                return Ok(result);
+            }
 
-        let expect = vec![
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("2")),
-            Token::Operator(Operator::Multiply),
-            Token::Value(String::from("2")),
-            Token::Brackets(Brackets::End),
-            Token::Operator(Operator::Plus),
-            Token::Value(String::from("1")),
-        ];
+            // z^w = exp(w * ln z)
+            let (ln_re, ln_im) = (a.hypot(b).ln(), b.atan2(a));
+            let (re, im) = (c * ln_re - d * ln_im, c * ln_im + d * ln_re);
+            Ok(complex(re.exp() * im.cos(), re.exp() * im.sin()))
+        },
+        _ => Err(String::from("複素数には使用できない演算子です。")),
+    }
+}
 
-        assert_eq!(expect, adjust_tokens);
+///
+/// 実部と虚部から計算結果を取得します
+/// 虚部が 0 の場合は数値とします
+///
+fn complex(re: f64, im: f64) -> Answer {
+    if im == 0.0 {
+        Answer::Number(re)
+    } else {
+        Answer::Complex(re, im)
     }
+}
 
-    #[test]
-    fn adjust_brackets_test2() {
-        let tokens = tokenize("2 + 2 / 1").unwrap();
-        let adjust_tokens = adjust_brackets(&tokens);
+///
+/// 数値・複素数を実部と虚部の組として取得します
+///
+fn to_complex(value: &Answer) -> Result<(f64, f64), String> {
+    match value {
+        Answer::Number(value) => Ok((*value, 0.0)),
+        Answer::Complex(re, im) => Ok((*re, *im)),
+        value => Err(value.unexpected("数値")),
+    }
+}
 
-        let expect = vec![
-            Token::Value(String::from("2")),
-            Token::Operator(Operator::Plus),
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("2")),
-            Token::Operator(Operator::Divide),
-            Token::Value(String::from("1")),
-            Token::Brackets(Brackets::End),
-        ];
+///
+/// 数値・複素数、またはリストの全ての要素に、実部と虚部を引数とする関数を適用します
+///
+fn map_complex(
+    value: &Answer, function: &impl Fn(f64, f64) -> Result<Answer, String>
+) -> Result<Answer, String> {
+    match value {
+        Answer::List(values) => values.iter()
+            .map(|value| map_complex(value, function))
+            .collect::<Result<Vec<Answer>, String>>()
+            .map(Answer::List),
+        value => {
+            let (re, im) = to_complex(value)?;
+            function(re, im)
+        },
+    }
+}
 
-        assert_eq!(expect, adjust_tokens);
+///
+/// 要素ごとに計算するリスト同士で、対応するリストの要素数が全て一致しているか判定します
+///
+fn is_same_length(lhs: &Answer, rhs: &Answer) -> bool {
+    match (lhs, rhs) {
+        (Answer::List(lhs), Answer::List(rhs)) => {
+            lhs.len() == rhs.len() &&
+                lhs.iter().zip(rhs).all(|(lhs, rhs)| is_same_length(lhs, rhs))
+        },
+        _ => true,
     }
+}
 
-    #[test]
-    fn adjust_brackets_test3() {
-        let tokens = tokenize("1 * 2 + 3 / 4").unwrap();
-        let adjust_tokens = adjust_brackets(&tokens);
+///
+/// リストの大きさを「2x3」のような文字列で取得します
+/// 要素の大きさが揃っていない場合は外側の要素数のみとします
+///
+fn shape(value: &Answer) -> String {
+    let Answer::List(values) = value else {
+        return value.kind().to_string();
+    };
 
-        let expect = vec![
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("1")),
-            Token::Operator(Operator::Multiply),
-            Token::Value(String::from("2")),
-            Token::Brackets(Brackets::End),
-            Token::Operator(Operator::Plus),
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("3")),
-            Token::Operator(Operator::Divide),
-            Token::Value(String::from("4")),
-            Token::Brackets(Brackets::End),
-        ];
+    match values.first() {
+        Some(first @ Answer::List(_)) => {
+            let inner = shape(first);
+            if values.iter().all(|value| shape(value) == inner) {
+                format!("{}x{inner}", values.len())
+            } else {
+                values.len().to_string()
+            }
+        },
+        _ => values.len().to_string(),
+    }
+}
 
-        assert_eq!(expect, adjust_tokens);
+///
+/// 数値、またはリストの全ての要素に関数を適用します
+///
+fn map_numbers(
+    value: &Answer, function: &impl Fn(f64) -> Result<f64, String>
+) -> Result<Answer, String> {
+    match value {
+        Answer::List(values) => values.iter()
+            .map(|value| map_numbers(value, function))
+            .collect::<Result<Vec<Answer>, String>>()
+            .map(Answer::List),
+        value => Ok(Answer::Number(function(value.as_number()?)?)),
     }
+}
 
-    #[test]
-    fn adjust_brackets_test4() {
-        let tokens = tokenize("(0 + 1 * 2) - 4 / 2").unwrap();
-        let adjust_tokens = adjust_brackets(&tokens);
+///
+/// 2つの値を比較演算子で比較します
+/// 「==」「!=」は真偽値同士・複素数同士も比較でき、それ以外は数値同士のみ比較できます
+///
+fn compare(lhs: &Answer, rhs: &Answer, operator: &Value) -> Result<bool, String> {
+    match operator {
+        Value::Op(Operator::Equal) | Value::Op(Operator::NotEqual) => {
+            let equal = match (lhs, rhs) {
+                (Answer::Bool(lhs), Answer::Bool(rhs)) => lhs == rhs,
+                (lhs, rhs) => to_complex(lhs)? == to_complex(rhs)?,
+            };
+            Ok(equal == (*operator == Value::Op(Operator::Equal)))
+        },
+        Value::Op(Operator::Less) => Ok(lhs.as_number()? < rhs.as_number()?),
+        Value::Op(Operator::LessEqual) => Ok(lhs.as_number()? <= rhs.as_number()?),
+        Value::Op(Operator::Greater) => Ok(lhs.as_number()? > rhs.as_number()?),
+        Value::Op(Operator::GreaterEqual) => Ok(lhs.as_number()? >= rhs.as_number()?),
+        _ => Err(String::from("比較演算子を想定していましたが、比較演算子以外が出現しました。")),
+    }
+}
 
-        let expect = vec![
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("0")),
-            Token::Operator(Operator::Plus),
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("1")),
-            Token::Operator(Operator::Multiply),
-            Token::Value(String::from("2")),
-            Token::Brackets(Brackets::End),
-            Token::Brackets(Brackets::End),
-            Token::Operator(Operator::Minus),
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("4")),
-            Token::Operator(Operator::Divide),
-            Token::Value(String::from("2")),
-            Token::Brackets(Brackets::End),
-        ];
+///
+/// 組み込み関数を呼び出します
+///
+fn call_function(name: &str, arguments: &[Answer]) -> Result<Answer, String> {
+    // 複素数を引数とする関数
+    // リストの場合は要素ごとに計算する
+    if let Some(function) = complex_function(name) {
+        check_arity(name, arguments, 1)?;
+        return map_complex(&arguments[0], &|re, im| Ok(function(re, im)));
+    }
 
-        assert_eq!(expect, adjust_tokens);
+    // 引数が1つの関数
+    // リストの場合は要素ごとに計算する
+    if let Some(function) = elementary_function(name) {
+        check_arity(name, arguments, 1)?;
+        return map_numbers(&arguments[0], &|value| Ok(function(value)));
     }
 
-    #[test]
-    fn adjust_brackets_test5() {
-        let tokens = tokenize("1 * (2 + 3) / 4").unwrap();
-        let adjust_tokens = adjust_brackets(&tokens);
+    match name {
+        // log(x, base)
+        "log" => {
+            check_arity(name, arguments, 2)?;
+            let value = arguments[0].as_number()?;
+            let base = arguments[1].as_number()?;
+            Ok(Answer::Number(value.log(base)))
+        },
+        // polar(r, θ) で絶対値と偏角から複素数を取得する
+        "polar" => {
+            check_arity(name, arguments, 2)?;
+            let r = arguments[0].as_number()?;
+            let theta = arguments[1].as_number()?;
+            Ok(complex(r * theta.cos(), r * theta.sin()))
+        },
+        // atan2(y, x)
+        "atan2" => {
+            check_arity(name, arguments, 2)?;
+            let y = arguments[0].as_number()?;
+            let x = arguments[1].as_number()?;
+            Ok(Answer::Number(y.atan2(x)))
+        },
+        // 任意の個数の引数を取る関数
+        // リストを指定した場合はリストの要素を引数とする
+        "min" | "max" | "avg" => {
+            let values = to_numbers(arguments)?;
+            check_min_arity(name, &values, 1)?;
+            Ok(Answer::Number(match name {
+                "min" => values.iter().fold(f64::INFINITY, |acc, value| acc.min(*value)),
+                "max" => values.iter().fold(f64::NEG_INFINITY, |acc, value| acc.max(*value)),
+                _ => values.iter().sum::<f64>() / values.len() as f64,
+            }))
+        },
+        "sum" => {
+            let values = to_numbers(arguments)?;
+            Ok(Answer::Number(values.iter().fold(0.0, |acc, value| acc + value)))
+        },
+        "hypot" => {
+            let values = to_numbers(arguments)?;
+            Ok(Answer::Number(values.iter().fold(0.0, |acc, value| acc.hypot(*value))))
+        },
+        "len" => {
+            check_arity(name, arguments, 1)?;
+            Ok(Answer::Number(arguments[0].as_list()?.len() as f64))
+        },
+        // 行列の関数
+        "transpose" => {
+            check_arity(name, arguments, 1)?;
+            let matrix = to_matrix(&arguments[0])?;
+            let transposed = (0..matrix[0].len())
+                .map(|column| matrix.iter().map(|row| row[column]).collect())
+                .collect();
+            Ok(from_matrix(transposed))
+        },
+        "det" => {
+            check_arity(name, arguments, 1)?;
+            let matrix = to_square_matrix(name, &arguments[0])?;
+            Ok(Answer::Number(determinant(matrix)))
+        },
+        "inv" => {
+            check_arity(name, arguments, 1)?;
+            let matrix = to_square_matrix(name, &arguments[0])?;
+            let identity = (0..matrix.len())
+                .map(|row| (0..matrix.len())
+                    .map(|column| if row == column { 1.0 } else { 0.0 })
+                    .collect())
+                .collect();
+            Ok(from_matrix(solve_linear(matrix, identity)?))
+        },
+        // solve(A, b) で A x = b を満たす x を取得する
+        // b が数値のリストの場合は x も数値のリストとする
+        "solve" => {
+            check_arity(name, arguments, 2)?;
+            let matrix = to_square_matrix(name, &arguments[0])?;
+            let vector = is_vector(&arguments[1]);
+            let values = if vector {
+                to_numbers(&arguments[1..])?.into_iter().map(|value| vec![value]).collect()
+            } else {
+                to_matrix(&arguments[1])?
+            };
 
-        let expect = vec![
-            Token::Value(String::from("1")),
-            Token::Operator(Operator::Multiply),
-            Token::Brackets(Brackets::Start),
-            Token::Value(String::from("2")),
-            Token::Operator(Operator::Plus),
-            Token::Value(String::from("3")),
-            Token::Brackets(Brackets::End),
-            Token::Operator(Operator::Divide),
-            Token::Value(String::from("4")),
-        ];
+            if matrix.len() != values.len() {
+                return Err(format!(
+                    "行列の大きさが一致しません。（{} と {}）",
+                    shape(&arguments[0]), shape(&arguments[1])
+                ));
+            }
 
-        assert_eq!(expect, adjust_tokens);
+            let solution = solve_linear(matrix, values)?;
+            if vector {
+                Ok(Answer::from(solution.into_iter().map(|row| row[0]).collect::<Vec<f64>>()))
+            } else {
+                Ok(from_matrix(solution))
+            }
+        },
+        _ => Err(format!("定義されていない関数です。「{name}」")),
     }
+}
 
-    //----- Block構造体の execute test ------------------------------------------
-    // 1 + 2
-    #[test]
-    fn block_execute_plus_test() {
-        let block = Block::new(
-            Value::Val(String::from("1")),
-            Value::Val(String::from("2")),
-            Value::Op(Operator::Plus),
-        );
+///
+/// 複素数を引数とする組み込み関数を取得します
+/// 引数は実部と虚部を指定します
+/// 該当する関数が存在しない場合は None を返却します
+///
+fn complex_function(name: &str) -> Option<fn(f64, f64) -> Answer> {
+    Some(match name {
+        // 負数の平方根は虚数とする（sqrt(-4) = 2i）
+        "sqrt" => |re, im| {
+            if im == 0.0 && (re >= 0.0 || re.is_nan()) {
+                return Answer::Number(re.sqrt());
+            }
+            let r = re.hypot(im);
+            let sqrt_im = ((r - re) / 2.0).sqrt();
+            complex(((r + re) / 2.0).sqrt(), if im < 0.0 { -sqrt_im } else { sqrt_im })
+        },
+        "abs" => |re, im| Answer::Number(re.hypot(im)),
+        "re" => |re, _| Answer::Number(re),
+        "im" => |_, im| Answer::Number(im),
+        "arg" => |re, im| Answer::Number(im.atan2(re)),
+        "conj" => |re, im| complex(re, -im),
+        _ => return None,
+    })
+}
 
-        assert_eq!("3", block.execute().unwrap().as_str());
-    }
+///
+/// 組み込み関数の名前か判定します
+///
+fn is_builtin_function(name: &str) -> bool {
+    elementary_function(name).is_some() || complex_function(name).is_some() ||
+        matches!(
+            name,
+            "log" | "atan2" | "polar" | "min" | "max" | "avg" | "sum" | "hypot" | "len" |
+            "transpose" | "det" | "inv" | "solve"
+        )
+}
 
-    // 1 - 2
-    #[test]
-    fn block_execute_minus_test() {
-        let block = Block::new(
-            Value::Val(String::from("1")),
-            Value::Val(String::from("2")),
-            Value::Op(Operator::Minus),
-        );
+///
+/// 引数が1つの組み込み関数を取得します
+/// 該当する関数が存在しない場合は None を返却します
+///
+fn elementary_function(name: &str) -> Option<fn(f64) -> f64> {
+    Some(match name {
+        "cbrt" => f64::cbrt,
+        "exp" => f64::exp,
+        "ln" => f64::ln,
+        "log10" => f64::log10,
+        "log2" => f64::log2,
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        "sinh" => f64::sinh,
+        "cosh" => f64::cosh,
+        "tanh" => f64::tanh,
+        "asinh" => f64::asinh,
+        "acosh" => f64::acosh,
+        "atanh" => f64::atanh,
+        "floor" => f64::floor,
+        "ceil" => f64::ceil,
+        "round" => f64::round,
+        "trunc" => f64::trunc,
+        "sign" => sign,
+        _ => return None,
+    })
+}
 
-        assert_eq!("-1", block.execute().unwrap().as_str());
+///
+/// 関数の引数の個数を確認します
+///
+fn check_arity(name: &str, arguments: &[Answer], count: usize) -> Result<(), String> {
+    if arguments.len() != count {
+        return Err(format!(
+            "関数「{name}」の引数は{count}個です。（{}個指定されました）", arguments.len()
+        ));
     }
+    Ok(())
+}
 
-    // 2 * 3
-    #[test]
-    fn block_execute_multiply_test() {
-        let block = Block::new(
-            Value::Val(String::from("2")),
-            Value::Val(String::from("3")),
-            Value::Op(Operator::Multiply),
-        );
-
-        assert_eq!("6", block.execute().unwrap().as_str());
+///
+/// 任意の個数の引数を取る関数で、引数が最低限の個数あるか確認します
+///
+fn check_min_arity<T>(name: &str, arguments: &[T], count: usize) -> Result<(), String> {
+    if arguments.len() < count {
+        return Err(format!(
+            "関数「{name}」の引数は{count}個以上必要です。（{}個指定されました）", arguments.len()
+        ));
+    }
+    Ok(())
+}
+
+///
+/// 関数の引数を全て数値として取得します
+/// リストの場合はリストの要素を全て取得します
+///
+fn to_numbers(arguments: &[Answer]) -> Result<Vec<f64>, String> {
+    let mut values = Vec::new();
+    for argument in arguments {
+        match argument {
+            Answer::List(list) => values.extend(to_numbers(list)?),
+            value => values.push(value.as_number()?),
+        }
+    }
+    Ok(values)
+}
+
+///
+/// 数値のみを要素とするリスト（ベクトル）か判定します
+///
+fn is_vector(value: &Answer) -> bool {
+    match value {
+        Answer::List(values) => values.iter().all(|value| !matches!(value, Answer::List(_))),
+        _ => false,
+    }
+}
+
+///
+/// [[1, 2], [3, 4]] のように数値のリストを行とするリストを行列として取得します
+/// 行が存在しない場合、各行の要素数が異なる場合はエラーとします
+///
+fn to_matrix(value: &Answer) -> Result<Vec<Vec<f64>>, String> {
+    let matrix = value.as_list()?.iter()
+        .map(|row| row.as_list()?.iter().map(Answer::as_number).collect())
+        .collect::<Result<Vec<Vec<f64>>, String>>()?;
+
+    match matrix.first() {
+        None => Err(String::from("行列の要素がありません。")),
+        Some(first) if first.is_empty() || matrix.iter().any(|row| row.len() != first.len()) => {
+            Err(format!("行列の各行の要素数が一致しません。「{value}」"))
+        },
+        Some(_) => Ok(matrix),
+    }
+}
+
+///
+/// 正方行列として取得します
+///
+fn to_square_matrix(name: &str, value: &Answer) -> Result<Vec<Vec<f64>>, String> {
+    let matrix = to_matrix(value)?;
+    if matrix.len() != matrix[0].len() {
+        return Err(format!(
+            "関数「{name}」の引数は正方行列である必要があります。（{}）", shape(value)
+        ));
+    }
+    Ok(matrix)
+}
+
+///
+/// 行列を計算結果として取得します
+///
+fn from_matrix(matrix: Vec<Vec<f64>>) -> Answer {
+    Answer::List(matrix.into_iter().map(Answer::from).collect())
+}
+
+///
+/// 行列の積を計算します
+/// 数値のリストは左辺の場合は行ベクトル、右辺の場合は列ベクトルとして扱い、
+/// 計算結果も数値のリストとします（ベクトル同士の場合は内積となります）
+///
+fn matrix_multiply(lhs: &Answer, rhs: &Answer) -> Result<Answer, String> {
+    let lhs_vector = is_vector(lhs);
+    let rhs_vector = is_vector(rhs);
+
+    let a = if lhs_vector {
+        vec![to_numbers(std::slice::from_ref(lhs))?]
+    } else {
+        to_matrix(lhs)?
+    };
+    let b = if rhs_vector {
+        to_numbers(std::slice::from_ref(rhs))?.into_iter().map(|value| vec![value]).collect()
+    } else {
+        to_matrix(rhs)?
+    };
+
+    // 空のリストはベクトルとして扱われるため、ここで要素が無いことを確認する
+    if a[0].is_empty() || b.is_empty() {
+        return Err(String::from("行列の要素がありません。"));
+    }
+    if a[0].len() != b.len() {
+        return Err(format!("行列の大きさが一致しません。（{} と {}）", shape(lhs), shape(rhs)));
+    }
+
+    let product = a.iter()
+        .map(|row| (0..b[0].len())
+            .map(|column| row.iter().zip(&b).map(|(value, b_row)| value * b_row[column]).sum())
+            .collect::<Vec<f64>>())
+        .collect::<Vec<Vec<f64>>>();
+
+    Ok(match (lhs_vector, rhs_vector) {
+        (true, true) => Answer::Number(product[0][0]),
+        (true, false) => Answer::from(product[0].clone()),
+        (false, true) => Answer::from(product.iter().map(|row| row[0]).collect::<Vec<f64>>()),
+        (false, false) => from_matrix(product),
+    })
+}
+
+///
+/// 部分ピボット選択付きのガウスの消去法で行列式を計算します
+///
+fn determinant(mut matrix: Vec<Vec<f64>>) -> f64 {
+    let size = matrix.len();
+    let tolerance = pivot_tolerance(&matrix);
+    let mut result = 1.0;
+
+    for column in 0..size {
+        // 絶対値が最大の行を軸とし、行を入れ替えた場合は符号を反転する
+        let pivot = (column..size)
+            .max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))
+            .unwrap();
+        if matrix[pivot][column].abs() <= tolerance {
+            return 0.0;
+        }
+        if pivot != column {
+            matrix.swap(pivot, column);
+            result = -result;
+        }
+
+        result *= matrix[column][column];
+        let pivot_row = matrix[column].clone();
+        for row in matrix.iter_mut().skip(column + 1) {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot_value) in row.iter_mut().zip(&pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    result
+}
+
+///
+/// 軸の値を0とみなす許容誤差を、行列の大きさと要素の絶対値の最大値から取得します
+/// det, inv, solve で正則かどうかの判定を揃えるため、同じ許容誤差を使用します
+///
+fn pivot_tolerance(matrix: &[Vec<f64>]) -> f64 {
+    let max = matrix.iter().flatten().fold(0.0_f64, |max, value| max.max(value.abs()));
+    max * matrix.len() as f64 * f64::EPSILON
+}
+
+///
+/// 部分ピボット選択付きのガウス・ジョルダン法で A X = B を満たす X を計算します
+/// A が正則でない場合はエラーとします
+///
+fn solve_linear(
+    mut matrix: Vec<Vec<f64>>, mut values: Vec<Vec<f64>>
+) -> Result<Vec<Vec<f64>>, String> {
+    let size = matrix.len();
+    let tolerance = pivot_tolerance(&matrix);
+
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))
+            .unwrap();
+        if matrix[pivot][column].abs() <= tolerance {
+            return Err(String::from("行列が正則ではないため計算できません。"));
+        }
+        matrix.swap(pivot, column);
+        values.swap(pivot, column);
+
+        // 軸の行を軸の値で割り、他の行から軸の列を消去する
+        let divisor = matrix[column][column];
+        matrix[column].iter_mut().for_each(|value| *value /= divisor);
+        values[column].iter_mut().for_each(|value| *value /= divisor);
+
+        let pivot_row = matrix[column].clone();
+        let pivot_values = values[column].clone();
+        for row in (0..size).filter(|row| *row != column) {
+            let factor = matrix[row][column];
+            for (value, pivot_value) in matrix[row].iter_mut().zip(&pivot_row) {
+                *value -= factor * pivot_value;
+            }
+            for (value, pivot_value) in values[row].iter_mut().zip(&pivot_values) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+///
+/// 数値の符号を取得します（正数は 1、負数は -1、0 は 0）
+///
+fn sign(value: f64) -> f64 {
+    if value == 0.0 || value.is_nan() {
+        value
+    } else {
+        value.signum()
+    }
+}
+
+///
+/// 階乗を計算します
+/// 整数以外の場合、設定によりガンマ関数を使用して計算します
+///
+fn factorial(value: f64, options: &Options) -> Result<f64, String> {
+    if value.fract() != 0.0 {
+        if !options.gamma_factorial {
+            return Err(format!("整数以外の階乗は計算できません。「{value}」"));
+        }
+        // n! = Γ(n + 1)
+        return Ok(gamma(value + 1.0));
+    }
+    if value < 0.0 {
+        return Err(format!("負の整数の階乗は計算できません。「{value}」"));
+    }
+
+    // 171! 以上は f64 の範囲を超えるため、計算を打ち切る
+    if value > 170.0 {
+        return Ok(f64::INFINITY);
+    }
+    Ok((1..=value as u64).fold(1.0, |acc, n| acc * n as f64))
+}
+
+///
+/// 二重階乗（n!! = n * (n - 2) * (n - 4) * ...）を計算します
+///
+fn double_factorial(value: f64) -> Result<f64, String> {
+    if value.fract() != 0.0 {
+        return Err(format!("整数以外の二重階乗は計算できません。「{value}」"));
+    }
+    if value < 0.0 {
+        return Err(format!("負の整数の二重階乗は計算できません。「{value}」"));
+    }
+
+    // 301!! 以上は f64 の範囲を超えるため、計算を打ち切る
+    if value > 300.0 {
+        return Ok(f64::INFINITY);
+    }
+    Ok((1..=value as u64).rev().step_by(2).fold(1.0, |acc, n| acc * n as f64))
+}
+
+///
+/// ガンマ関数を Lanczos 近似で計算します
+///
+fn gamma(value: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    // 0.5 未満の場合は相反公式 Γ(x)Γ(1 - x) = π / sin(πx) を使用する
+    if value < 0.5 {
+        return std::f64::consts::PI
+            / ((std::f64::consts::PI * value).sin() * gamma(1.0 - value));
+    }
+
+    let value = value - 1.0;
+    let t = value + G + 0.5;
+    let sum = COEFFICIENTS.iter().enumerate().skip(1)
+        .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (value + i as f64));
+
+    (2.0 * std::f64::consts::PI).sqrt() * t.powf(value + 0.5) * (-t).exp() * sum
+}
+
+///
+/// ビット演算のため、数値を整数に変換します
+/// 整数以外、または64ビット整数の範囲外の場合はエラーとします
+///
+fn to_integer(value: f64) -> Result<i64, String> {
+    if value.fract() != 0.0 || value < i64::MIN as f64 || value >= i64::MAX as f64 {
+        return Err(format!("整数以外にビット演算は使用できません。「{value}」"));
+    }
+    Ok(value as i64)
+}
+
+///
+/// ビットシフトのシフト量を取得します
+/// 0以上64未満の整数以外の場合はエラーとします
+///
+fn to_shift_amount(value: f64) -> Result<i64, String> {
+    let amount = to_integer(value)?;
+    if !(0..64).contains(&amount) {
+        return Err(format!("シフト量は0以上64未満で指定してください。「{value}」"));
+    }
+    Ok(amount)
+}
+
+///
+/// 数値文字列を数値に変換します
+///
+fn to_number(value: &str) -> Result<f64, String> {
+    value.parse::<f64>()
+        .map_err(|_| format!("数値に変換できませんでした。「{value}」"))
+}
+
+
+
+
+//----- TEST CODE --------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::Block;
+    use crate::Operator;
+    use super::*;
+
+    //----- tokenize test ------------------------------------------------------
+    #[test]
+    fn tokenize_test1() {
+        let expect = vec![
+            Token::Value(String::from("1")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Minus),
+            Token::Value(String::from("3")),
+        ];
+
+        let formula = String::from("1+2 - 3");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test2() {
+        let expect = vec![
+            Token::Value(String::from("100")),
+            Token::Operator(Operator::Multiply),
+            Token::Value(String::from("20")),
+            Token::Operator(Operator::Divide),
+            Token::Value(String::from("1505")),
+        ];
+
+        let formula = String::from("100 *20 /1505");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test3() {
+        let expect = vec![
+            Token::Value(String::from("12.34")),
+            Token::Operator(Operator::Minus),
+            Token::Value(String::from("5.678")),
+        ];
+
+        let formula = String::from("12.34-5.678");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test4() {
+        let expect = vec![
+            Token::Brackets(Brackets::Start(BracketKind::Round, 0)),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Multiply),
+            Token::Value(String::from("2")),
+            Token::Brackets(Brackets::End(BracketKind::Round, 6)),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("30")),
+        ];
+
+        let formula = String::from("(2 * 2) + 30");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test5() {
+        let expect = vec![
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Power),
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::Power),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Multiply),
+            Token::Value(String::from("4")),
+        ];
+
+        let formula = String::from("2 ** 3^2 * 4");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test6() {
+        let expect = vec![
+            Token::Value(String::from("7")),
+            Token::Operator(Operator::FloorDivide),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Modulo),
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::Divide),
+            Token::Value(String::from("1")),
+        ];
+
+        let formula = String::from("7 // 2 % 3/1");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test7() {
+        let expect = vec![
+            Token::Value(String::from("1.5e-3")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("6.02e23")),
+            Token::Operator(Operator::Minus),
+            Token::Value(String::from("1e+10")),
+        ];
+
+        let formula = String::from("1.5e-3 + 6.02E23 - 1e+10");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test8() {
+        let expect = vec![
+            Token::Value(String::from("31")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("10")),
+            Token::Operator(Operator::Minus),
+            Token::Value(String::from("15")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("65535")),
+        ];
+
+        let formula = String::from("0x1F + 0b1010 - 0o17 + 0xFF_FF");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test9() {
+        let expect = vec![
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::Factorial),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("5")),
+            Token::Operator(Operator::DoubleFactorial),
+        ];
+
+        let formula = String::from("3! + 5!!");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test10() {
+        let expect = vec![
+            Token::Value(String::from("200")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("10")),
+            Token::Operator(Operator::Percent),
+        ];
+
+        let options = Options {
+            percent_sign: PercentSign::Percent,
+            ..Options::default()
+        };
+        let tokens = tokenize("200 + 10%", &options).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test11() {
+        let expect = vec![
+            Token::Brackets(Brackets::Start(BracketKind::Curly, 0)),
+            Token::Brackets(Brackets::Start(BracketKind::Square, 1)),
+            Token::Value(String::from("1")),
+            Token::Brackets(Brackets::End(BracketKind::Square, 3)),
+            Token::Brackets(Brackets::End(BracketKind::Curly, 4)),
+        ];
+
+        let tokens = tokenize("{[1]}", &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test12() {
+        let expect = vec![
+            Token::Value(String::from("1")),
+            Token::Operator(Operator::Equal),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::NotEqual),
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::Less),
+            Token::Value(String::from("4")),
+            Token::Operator(Operator::LessEqual),
+            Token::Value(String::from("5")),
+            Token::Operator(Operator::Greater),
+            Token::Value(String::from("6")),
+            Token::Operator(Operator::GreaterEqual),
+            Token::Value(String::from("7")),
+        ];
+
+        let formula = String::from("1 == 2 != 3<4 <= 5 > 6>=7");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test13() {
+        let expect = vec![
+            Token::Operator(Operator::Not),
+            Token::Value(String::from("1")),
+            Token::Operator(Operator::And),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Or),
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::And),
+            Token::Operator(Operator::PrefixNot),
+            Token::Value(String::from("4")),
+            Token::Operator(Operator::Or),
+            Token::Value(String::from("5")),
+        ];
+
+        let formula = String::from("not 1 and 2 or 3 && !4 || 5");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test14() {
+        let expect = vec![
+            Token::Branch(Branch::If),
+            Token::Value(String::from("1")),
+            Token::Branch(Branch::Then),
+            Token::Value(String::from("2")),
+            Token::Branch(Branch::Else),
+            Token::Value(String::from("3")),
+            Token::Branch(Branch::Question),
+            Token::Value(String::from("4")),
+            Token::Branch(Branch::Colon),
+            Token::Value(String::from("5")),
+        ];
+
+        let formula = String::from("if 1 then 2 else 3 ? 4 : 5");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test15() {
+        let expect = vec![
+            Token::Value(String::from("1")),
+            Token::Operator(Operator::ShiftLeft),
+            Token::Value(String::from("7")),
+            Token::Operator(Operator::BitOr),
+            Token::Operator(Operator::BitNot),
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::BitAnd),
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::BitXor),
+            Token::Value(String::from("4")),
+            Token::Operator(Operator::ShiftRight),
+            Token::Value(String::from("5")),
+        ];
+
+        let formula = String::from("1 << 7 | ~2 & 3 xor 4 >> 5");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test16() {
+        let expect = vec![
+            Token::Value(String::from("2")),
+            Token::Operator(Operator::Multiply),
+            Token::Identifier(String::from("pi")),
+            Token::Operator(Operator::Plus),
+            Token::Identifier(String::from("π")),
+            Token::Operator(Operator::Minus),
+            Token::Identifier(String::from("rate_2")),
+        ];
+
+        let formula = String::from("2 * pi + π - rate_2");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test17() {
+        let expect = vec![
+            Token::Identifier(String::from("log")),
+            Token::Brackets(Brackets::Start(BracketKind::Round, 3)),
+            Token::Value(String::from("8")),
+            Token::Comma,
+            Token::Value(String::from("2")),
+            Token::Brackets(Brackets::End(BracketKind::Round, 8)),
+        ];
+
+        let formula = String::from("log(8, 2)");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test18() {
+        let expect = vec![
+            Token::Identifier(String::from("rate")),
+            Token::Assign,
+            Token::Identifier(String::from("rate")),
+            Token::Operator(Operator::Equal),
+            Token::Value(String::from("1")),
+        ];
+
+        let formula = String::from("rate = rate == 1");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test19() {
+        let expect = vec![
+            Token::Identifier(String::from("a")),
+            Token::Assign,
+            Token::Value(String::from("3")),
+            Token::Separator,
+            Token::Identifier(String::from("a")),
+            Token::Separator,
+            Token::Separator,
+        ];
+
+        let formula = String::from("a = 3;\ta\r\n;");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test20() {
+        let expect = vec![
+            Token::Value(String::from("1")),
+            Token::Separator,
+            Token::Brackets(Brackets::Start(BracketKind::Round, 9)),
+            Token::Value(String::from("2")),
+            Token::Brackets(Brackets::End(BracketKind::Round, 11)),
+        ];
+
+        let formula = String::from("1 # (1 +\n(2) # )");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test21() {
+        let options = Options {
+            double_slash: DoubleSlash::Comment,
+            ..Options::default()
+        };
+        let expect = vec![
+            Token::Value(String::from("7")),
+            Token::Separator,
+            Token::Value(String::from("2")),
+        ];
+
+        let tokens = tokenize("7 // 2\n2 //", &options).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test22() {
+        let expect = vec![
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::Plus),
+            Token::Imaginary(String::from("4")),
+            Token::Operator(Operator::Minus),
+            Token::Imaginary(String::from("2.5")),
+            Token::Operator(Operator::Multiply),
+            Token::Identifier(String::from("i")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("2")),
+            Token::Identifier(String::from("in")),
+        ];
+
+        let formula = String::from("3 + 4i - 2.5j * i + 2in");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_test23() {
+        let expect = vec![
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::Factorial),
+            Token::Operator(Operator::Equal),
+            Token::Value(String::from("6")),
+            Token::Operator(Operator::NotEqual),
+            Token::Value(String::from("1")),
+        ];
+
+        let formula = String::from("3!==6!=1");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    // 値の後以外の「!」は否定、値の後の「!」「!!」は階乗として扱う
+    #[test]
+    fn tokenize_test24() {
+        let expect = vec![
+            Token::Operator(Operator::PrefixNot),
+            Token::Operator(Operator::PrefixNot),
+            Token::Identifier(String::from("x")),
+            Token::Operator(Operator::NotEqual),
+            Token::Value(String::from("3")),
+            Token::Operator(Operator::DoubleFactorial),
+            Token::Operator(Operator::Factorial),
+        ];
+
+        let formula = String::from("!!x != 3!! !");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_err_test1() {
+        let formula = String::from("23.5 + 10..45");
+        let tokens = tokenize(&formula, &Options::default());
+
+        match tokens {
+            Ok(_) => {panic!("エラーが発生しませんでした。")},
+            Err(value) => assert_eq!("「.」が連続して出現しました。", value),
+        }
+    }
+
+    #[test]
+    fn tokenize_err_test2() {
+        let formula = String::from("1 + あ");
+        let tokens = tokenize(&formula, &Options::default());
+
+        match tokens {
+            Ok(_) => {panic!("エラーが発生しませんでした。")},
+            Err(value) => {
+                assert_eq!("予期せぬ文字が出現しました。「あ」", value)
+            },
+        }
+    }
+
+    #[test]
+    fn tokenize_err_test3() {
+        match tokenize("1e + 2", &Options::default()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("指数部の数字がありません。「1e」", value),
+        }
+
+        match tokenize("2.5E-", &Options::default()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("指数部の数字がありません。「2.5e-」", value),
+        }
+    }
+
+    #[test]
+    fn tokenize_err_test4() {
+        match tokenize("1e2.5", &Options::default()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("指数部に「.」は使用できません。「1e2.」", value),
+        }
+    }
+
+    #[test]
+    fn tokenize_err_test5() {
+        match tokenize("0b102", &Options::default()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("2進数に使用できない文字が出現しました。「0b102」", value),
+        }
+
+        match tokenize("0x_ + 1", &Options::default()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("16進数の数字がありません。「0x_」", value),
+        }
+
+        match tokenize("0x1_0000_0000_0000_0000", &Options::default()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "16進数の値が大きすぎます。「0x1_0000_0000_0000_0000」", value
+            ),
+        }
+    }
+
+    #[test]
+    fn tokenize_normalize_test1() {
+        let expect = vec![
+            Token::Brackets(Brackets::Start(BracketKind::Round, 0)),
+            Token::Value(String::from("12")),
+            Token::Operator(Operator::Plus),
+            Token::Value(String::from("3")),
+            Token::Brackets(Brackets::End(BracketKind::Round, 5)),
+            Token::Operator(Operator::Multiply),
+            Token::Value(String::from("4")),
+            Token::Operator(Operator::Divide),
+            Token::Value(String::from("5")),
+            Token::Operator(Operator::Minus),
+            Token::Value(String::from("6")),
+            Token::Operator(Operator::Minus),
+            Token::Value(String::from("7")),
+            Token::Operator(Operator::Multiply),
+            Token::Value(String::from("8")),
+        ];
+
+        let formula = String::from("（１２＋３）×４÷５－６−７・８");
+        let tokens = tokenize(&formula, &Options::default()).unwrap();
+
+        assert_eq!(expect, tokens);
+    }
+
+    #[test]
+    fn tokenize_normalize_err_test1() {
+        let options = Options {
+            normalize: false,
+            ..Options::default()
+        };
+
+        match tokenize("１２＋３", &options) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("予期せぬ文字が出現しました。「１」", value),
+        }
+    }
+
+    //----- parse_token test ---------------------------------------------------
+    #[test]
+    fn parse_token_test1() {
+        let formula = String::from("1 + 1");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("2"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test2() {
+        let formula = String::from("5 - 2 + 10");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("13"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test3() {
+        let formula = String::from("10 + 5 + 3 - 2");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("16"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test4() {
+        let formula = String::from("10 - -2");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("12"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test5() {
+        let formula = String::from("10.5 + -2.2");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("8.3"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test6() {
+        let formula = String::from("(2 + 2) * (3 + 3)");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("24"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test7() {
+        let formula = String::from("5 * (4 + 4)");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("40"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test8() {
+        let formula = String::from("(6 - 2) / 2");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("2"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test9() {
+        let formula = String::from("((2 + 2) * (5 + 5)) / 2");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("20"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test10() {
+        let formula = String::from("3 * (((5 + 5) * 2) + 10) / 2");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("45"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test11() {
+        let formula = String::from("10 * (((1 + 1) / 2) - 9)");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("-80"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test12() {
+        let formula = String::from("2 * 3 * 4 + 1");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("25"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test13() {
+        let formula = String::from("1 * (2 + 3) / 4");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("1.25"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test14() {
+        let formula = String::from("10 - 4 - 3 + 1");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("4"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_test15() {
+        let formula = String::from("(7)");
+        let tokens = tokenize(&formula, &Options::default());
+        let result = parse_token(&tokens.unwrap(), &mut Calculator::new()).map(|value| value.to_string());
+
+        assert_eq!(String::from("7"), result.unwrap());
+    }
+
+    #[test]
+    fn parse_token_all_test1() {
+        let tokens = tokenize("a = 3; b = 4\n\nsqrt(a^2 + b^2);", &Options::default()).unwrap();
+        let result = parse_token_all(&tokens, &mut Calculator::new()).unwrap();
+
+        assert_eq!(vec![Answer::Number(3.0), Answer::Number(4.0), Answer::Number(5.0)], result);
+    }
+
+    // 途中の文で解析に失敗した場合、前の文も計算しない
+    #[test]
+    fn parse_token_all_err_test1() {
+        let tokens = tokenize("a = 3; b = (4", &Options::default()).unwrap();
+        let mut calculator = Calculator::new();
+
+        match parse_token_all(&tokens, &mut calculator) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("終了かっこが不足しています。", value),
+        }
+        assert_eq!(None, calculator.get_variable("a"));
+    }
+
+    #[test]
+    fn parse_token_err_test1() {
+        let tokens = tokenize("(1 + 2", &Options::default()).unwrap();
+
+        match parse_token(&tokens, &mut Calculator::new()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("終了かっこが不足しています。", value),
+        }
+    }
+
+    #[test]
+    fn parse_token_err_test2() {
+        let tokens = tokenize("1 + 2)", &Options::default()).unwrap();
+
+        match parse_token(&tokens, &mut Calculator::new()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("想定外の終了かっこが出現しました。", value),
+        }
+    }
+
+    #[test]
+    fn parse_token_err_test3() {
+        let tokens = tokenize("2 (3 + 4)", &Options::default()).unwrap();
+
+        match parse_token(&tokens, &mut Calculator::new()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "演算子を期待していましたが、演算子以外が出現しました。", value
+            ),
+        }
+    }
+
+    #[test]
+    fn parse_token_err_test4() {
+        let tokens = tokenize("(1 + 2]", &Options::default()).unwrap();
+
+        match parse_token(&tokens, &mut Calculator::new()) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "かっこの種類が一致しません。「(」（1文字目）と「]」（7文字目）", value
+            ),
+        }
+    }
+
+    //----- Parser構造体の parse_expression test --------------------------------
+    // 1 + 2 * 3 → 1 + (2 * 3)
+    #[test]
+    fn parse_expression_precedence_test() {
+        let tokens = tokenize("1 + 2 * 3", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let expect = Value::Block(Box::new(Block::new(
+            Value::Val(String::from("1")),
+            Value::Block(Box::new(Block::new(
+                Value::Val(String::from("2")),
+                Value::Val(String::from("3")),
+                Value::Op(Operator::Multiply),
+            ))),
+            Value::Op(Operator::Plus),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    // 8 / 4 / 2 → (8 / 4) / 2
+    #[test]
+    fn parse_expression_associativity_test() {
+        let tokens = tokenize("8 / 4 / 2", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let expect = Value::Block(Box::new(Block::new(
+            Value::Block(Box::new(Block::new(
+                Value::Val(String::from("8")),
+                Value::Val(String::from("4")),
+                Value::Op(Operator::Divide),
+            ))),
+            Value::Val(String::from("2")),
+            Value::Op(Operator::Divide),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    // -2 * 3 → (-2) * 3
+    #[test]
+    fn parse_expression_unary_test() {
+        let tokens = tokenize("-2 * 3", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let expect = Value::Block(Box::new(Block::new(
+            Value::Unary(Box::new(Unary::new(
+                Value::Val(String::from("2")),
+                Value::Op(Operator::Minus),
+            ))),
+            Value::Val(String::from("3")),
+            Value::Op(Operator::Multiply),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    // 1 / 2(3) → 1 / (2 * 3)
+    #[test]
+    fn parse_expression_implicit_multiply_test() {
+        let options = Options {
+            implicit_multiplication: ImplicitMultiplication::HigherPrecedence,
+            ..Options::default()
+        };
+        let tokens = tokenize("1 / 2(3)", &options).unwrap();
+        let value = Parser::new(&tokens, &options).parse_expression(0).unwrap();
+
+        let expect = Value::Block(Box::new(Block::new(
+            Value::Val(String::from("1")),
+            Value::Block(Box::new(Block::new(
+                Value::Val(String::from("2")),
+                Value::Val(String::from("3")),
+                Value::Op(Operator::Multiply),
+            ))),
+            Value::Op(Operator::Divide),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    // 0 <= 5 + 1 < 10 → 0 <= (5 + 1) かつ (5 + 1) < 10
+    #[test]
+    fn parse_expression_comparison_test() {
+        let tokens = tokenize("0 <= 5 + 1 < 10", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let mut comparison = Comparison::new(Value::Val(String::from("0")));
+        comparison.push(
+            Value::Op(Operator::LessEqual),
+            Value::Block(Box::new(Block::new(
+                Value::Val(String::from("5")),
+                Value::Val(String::from("1")),
+                Value::Op(Operator::Plus),
+            ))),
+        );
+        comparison.push(Value::Op(Operator::Less), Value::Val(String::from("10")));
+
+        assert_eq!(Value::Comparison(Box::new(comparison)), value);
+    }
+
+    // 1 > 2 ? 1 : 2 > 1 ? 2 : 3 → 1 > 2 ? 1 : (2 > 1 ? 2 : 3)
+    #[test]
+    fn parse_expression_ternary_test() {
+        let tokens = tokenize("1 > 2 ? 1 : 2 > 1 ? 2 : 3", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let mut condition1 = Comparison::new(Value::Val(String::from("1")));
+        condition1.push(Value::Op(Operator::Greater), Value::Val(String::from("2")));
+        let mut condition2 = Comparison::new(Value::Val(String::from("2")));
+        condition2.push(Value::Op(Operator::Greater), Value::Val(String::from("1")));
+
+        let expect = Value::Conditional(Box::new(Conditional::new(
+            Value::Comparison(Box::new(condition1)),
+            Value::Val(String::from("1")),
+            Value::Conditional(Box::new(Conditional::new(
+                Value::Comparison(Box::new(condition2)),
+                Value::Val(String::from("2")),
+                Value::Val(String::from("3")),
+            ))),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    // sqrt(1 + 3) * 2
+    #[test]
+    fn parse_expression_call_test() {
+        let tokens = tokenize("sqrt(1 + 3) * 2", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let expect = Value::Block(Box::new(Block::new(
+            Value::Call(Box::new(Call::new("sqrt", vec![
+                Value::Block(Box::new(Block::new(
+                    Value::Val(String::from("1")),
+                    Value::Val(String::from("3")),
+                    Value::Op(Operator::Plus),
+                ))),
+            ]))),
+            Value::Val(String::from("2")),
+            Value::Op(Operator::Multiply),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    // -[1, 2][0] → -([1, 2][0])
+    #[test]
+    fn parse_expression_list_test() {
+        let tokens = tokenize("-[1, 2][0]", &Options::default()).unwrap();
+        let value = Parser::new(&tokens, &Options::default()).parse_expression(0).unwrap();
+
+        let expect = Value::Unary(Box::new(Unary::new(
+            Value::Index(Box::new(Index::new(
+                Value::List(vec![
+                    Value::Val(String::from("1")),
+                    Value::Val(String::from("2")),
+                ]),
+                Value::Val(String::from("0")),
+            ))),
+            Value::Op(Operator::Minus),
+        )));
+
+        assert_eq!(expect, value);
+    }
+
+    //----- Block構造体の execute test ------------------------------------------
+    // 1 + 2
+    #[test]
+    fn block_execute_plus_test() {
+        let block = Block::new(
+            Value::Val(String::from("1")),
+            Value::Val(String::from("2")),
+            Value::Op(Operator::Plus),
+        );
+
+        assert_eq!("3", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 1 - 2
+    #[test]
+    fn block_execute_minus_test() {
+        let block = Block::new(
+            Value::Val(String::from("1")),
+            Value::Val(String::from("2")),
+            Value::Op(Operator::Minus),
+        );
+
+        assert_eq!("-1", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 2 * 3
+    #[test]
+    fn block_execute_multiply_test() {
+        let block = Block::new(
+            Value::Val(String::from("2")),
+            Value::Val(String::from("3")),
+            Value::Op(Operator::Multiply),
+        );
+
+        assert_eq!("6", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
     }
 
     // 10 / 5
     #[test]
-    fn block_execute_divide_test() {
-        let block = Block::new(
-            Value::Val(String::from("10")),
-            Value::Val(String::from("5")),
-            Value::Op(Operator::Divide),
-        );
+    fn block_execute_divide_test() {
+        let block = Block::new(
+            Value::Val(String::from("10")),
+            Value::Val(String::from("5")),
+            Value::Op(Operator::Divide),
+        );
+
+        assert_eq!("2", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 4 * 4 - 6
+    #[test]
+    fn block_execute_inner_block_left_test() {
+        let block = Block::new(
+            Value::Val(String::from("4")),
+            Value::Val(String::from("4")),
+            Value::Op(Operator::Multiply),
+        );
+
+        let block = Block::new(
+            Value::Block(Box::new(block)),
+            Value::Val(String::from("6")),
+            Value::Op(Operator::Minus),
+        );
+
+        assert_eq!("10", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // (5 + 5) / 2
+    #[test]
+    fn block_execute_inner_block_right_test() {
+        let block = Block::new(
+            Value::Val(String::from("5")),
+            Value::Val(String::from("5")),
+            Value::Op(Operator::Plus),
+        );
+
+        let block = Block::new(
+            Value::Val(String::from("2")),
+            Value::Block(Box::new(block)),
+            Value::Op(Operator::Divide),
+        );
+
+        assert_eq!("0.2", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // (3 + 7) * (6 + 4)
+    #[test]
+    fn block_execute_inner_block_left_right_test() {
+        let block_l = Block::new(
+            Value::Val(String::from("3")),
+            Value::Val(String::from("7")),
+            Value::Op(Operator::Plus),
+        );
+
+        let block_r = Block::new(
+            Value::Val(String::from("6")),
+            Value::Val(String::from("4")),
+            Value::Op(Operator::Plus),
+        );
+
+        let block = Block::new(
+            Value::Block(Box::new(block_l)),
+            Value::Block(Box::new(block_r)),
+            Value::Op(Operator::Multiply),
+        );
+
+        assert_eq!("100", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 24 / (2 * 2 + 4)
+    #[test]
+    fn block_execute_inner_block_double_test() {
+        let block = Block::new(
+            Value::Val(String::from("2")),
+            Value::Val(String::from("2")),
+            Value::Op(Operator::Multiply),
+        );
+
+        let block = Block::new(
+            Value::Block(Box::new(block)),
+            Value::Val(String::from("4")),
+            Value::Op(Operator::Plus),
+        );
+
+        let block = Block::new(
+            Value::Val(String::from("24")),
+            Value::Block(Box::new(block)),
+            Value::Op(Operator::Divide),
+        );
+
+        assert_eq!("3", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 2 ^ 10
+    #[test]
+    fn block_execute_power_test() {
+        let block = Block::new(
+            Value::Val(String::from("2")),
+            Value::Val(String::from("10")),
+            Value::Op(Operator::Power),
+        );
+
+        assert_eq!("1024", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 1 / 0
+    #[test]
+    fn block_execute_divide_zero_test() {
+        let block = Block::new(
+            Value::Val(String::from("1")),
+            Value::Val(String::from("0")),
+            Value::Op(Operator::Divide),
+        );
+
+        assert_eq!("inf", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // 1 > 2 and 1 % 0 > 2
+    #[test]
+    fn block_execute_and_short_circuit_test() {
+        let mut lhs = Comparison::new(Value::Val(String::from("1")));
+        lhs.push(Value::Op(Operator::Greater), Value::Val(String::from("2")));
+
+        let mut rhs = Comparison::new(Value::Block(Box::new(Block::new(
+            Value::Val(String::from("1")),
+            Value::Val(String::from("0")),
+            Value::Op(Operator::Modulo),
+        ))));
+        rhs.push(Value::Op(Operator::Greater), Value::Val(String::from("2")));
+
+        let block = Block::new(
+            Value::Comparison(Box::new(lhs)),
+            Value::Comparison(Box::new(rhs)),
+            Value::Op(Operator::And),
+        );
+
+        assert_eq!("false", block.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    //----- Unary構造体の execute test ------------------------------------------
+    // -(2 + 3)
+    #[test]
+    fn unary_execute_minus_test() {
+        let block = Block::new(
+            Value::Val(String::from("2")),
+            Value::Val(String::from("3")),
+            Value::Op(Operator::Plus),
+        );
+
+        let unary = Unary::new(
+            Value::Block(Box::new(block)),
+            Value::Op(Operator::Minus),
+        );
+
+        assert_eq!("-5", unary.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    // +4
+    #[test]
+    fn unary_execute_plus_test() {
+        let unary = Unary::new(
+            Value::Val(String::from("4")),
+            Value::Op(Operator::Plus),
+        );
+
+        assert_eq!("4", unary.execute(&mut Calculator::new()).unwrap().to_string().as_str());
+    }
+
+    //----- Comparison構造体の execute test -------------------------------------
+    // 3 < 2 < 1 % 0
+    #[test]
+    fn comparison_execute_short_circuit_test() {
+        let mut comparison = Comparison::new(Value::Val(String::from("3")));
+        comparison.push(Value::Op(Operator::Less), Value::Val(String::from("2")));
+        comparison.push(
+            Value::Op(Operator::Less),
+            Value::Block(Box::new(Block::new(
+                Value::Val(String::from("1")),
+                Value::Val(String::from("0")),
+                Value::Op(Operator::Modulo),
+            ))),
+        );
+
+        assert_eq!(Answer::Bool(false), comparison.execute(&mut Calculator::new()).unwrap());
+    }
+
+    //----- call_function test -----------------------------------------------
+    #[test]
+    fn call_function_test() {
+        let call = |name: &str, arguments: &[f64]| {
+            let arguments = arguments.iter()
+                .map(|value| Answer::Number(*value))
+                .collect::<Vec<Answer>>();
+            call_function(name, &arguments).unwrap().as_number().unwrap()
+        };
+
+        assert_eq!(4.0, call("sqrt", &[16.0]));
+        assert_eq!(3.0, call("cbrt", &[27.0]));
+        assert_eq!(3.0, call("abs", &[-3.0]));
+        assert_eq!(1.0, call("exp", &[0.0]));
+        assert_eq!(1.0, call("ln", &[std::f64::consts::E]));
+        assert_eq!(3.0, call("log10", &[1000.0]));
+        assert_eq!(3.0, call("log2", &[8.0]));
+        assert_eq!(2.0, call("log", &[100.0, 10.0]));
+        assert_eq!(-1.0, call("cos", &[std::f64::consts::PI]));
+        assert_eq!(std::f64::consts::FRAC_PI_2, call("asin", &[1.0]));
+        assert_eq!(0.0, call("tanh", &[0.0]));
+        assert_eq!(-3.0, call("floor", &[-2.5]));
+        assert_eq!(3.0, call("ceil", &[2.1]));
+        assert_eq!(3.0, call("round", &[2.5]));
+        assert_eq!(-2.0, call("trunc", &[-2.7]));
+        assert_eq!(-1.0, call("sign", &[-5.0]));
+        assert_eq!(0.0, call("sign", &[0.0]));
+        assert_eq!(std::f64::consts::FRAC_PI_4, call("atan2", &[1.0, 1.0]));
+    }
+
+    #[test]
+    fn call_function_variadic_test() {
+        let call = |name: &str, arguments: &[f64]| {
+            let arguments = arguments.iter()
+                .map(|value| Answer::Number(*value))
+                .collect::<Vec<Answer>>();
+            call_function(name, &arguments).unwrap().as_number().unwrap()
+        };
+
+        assert_eq!(7.0, call("max", &[3.0, 7.0, 2.0]));
+        assert_eq!(-1.0, call("min", &[3.0, -1.0, 2.0]));
+        assert_eq!(5.0, call("min", &[5.0]));
+        assert_eq!(2.5, call("avg", &[1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(10.0, call("sum", &[1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(0.0, call("sum", &[]));
+        assert_eq!(5.0, call("hypot", &[3.0, 4.0]));
+        assert_eq!(3.0, call("hypot", &[1.0, 2.0, 2.0]));
+    }
+
+    //----- factorial test -----------------------------------------------------
+    #[test]
+    fn factorial_test() {
+        let options = Options::default();
+
+        assert_eq!(1.0, factorial(0.0, &options).unwrap());
+        assert_eq!(3628800.0, factorial(10.0, &options).unwrap());
+        assert_eq!(f64::INFINITY, factorial(171.0, &options).unwrap());
+    }
+
+    #[test]
+    fn factorial_gamma_test() {
+        let options = Options {
+            gamma_factorial: true,
+            ..Options::default()
+        };
+
+        let half = std::f64::consts::PI.sqrt() / 2.0;
+        assert!((factorial(0.5, &options).unwrap() - half).abs() < 1e-12);
+        assert!((factorial(-0.5, &options).unwrap() - half * 2.0).abs() < 1e-12);
+        assert!((factorial(4.5, &options).unwrap() - 52.34277778455352).abs() < 1e-9);
+    }
+
+    #[test]
+    fn double_factorial_test() {
+        assert_eq!(1.0, double_factorial(0.0).unwrap());
+        assert_eq!(105.0, double_factorial(7.0).unwrap());
+        assert_eq!(384.0, double_factorial(8.0).unwrap());
+    }
+
+    //----- calculation test ---------------------------------------------------
+    #[test]
+    fn calculation_test1() {
+        let result = calculation("10 * 2 + 1").unwrap();
+        assert_eq!(String::from("21"), result);
+    }
+
+    #[test]
+    fn calculation_test2() {
+        let result = calculation("10 * 2 + 1 - 2 + 10 / 2").unwrap();
+        assert_eq!(String::from("24"), result);
+    }
+
+    #[test]
+    fn calculation_test3() {
+        let result = calculation("(2.5 + 1.3) * 2").unwrap();
+        assert_eq!(String::from("7.6"), result);
+    }
+
+    #[test]
+    fn calculation_test4() {
+        let result = calculation("(3 - 4 * 2) / (9 / 3 - 1)").unwrap();
+        assert_eq!(String::from("-2.5"), result);
+    }
+
+    #[test]
+    fn calculation_test5() {
+        let result = calculation("((2 * (1 + 1)) + 3) / 2").unwrap();
+        assert_eq!(String::from("3.5"), result);
+    }
+
+    #[test]
+    fn calculation_test6() {
+        let result = calculation("1 + 2 * 3 - 4 / 2 * 3").unwrap();
+        assert_eq!(String::from("1"), result);
+    }
+
+    #[test]
+    fn calculation_test7() {
+        assert_eq!(String::from("-2"), calculation("-5 + 3").unwrap());
+        assert_eq!(String::from("-5"), calculation("-(2 + 3)").unwrap());
+        assert_eq!(String::from("-4"), calculation("2 * -(1 + 1)").unwrap());
+        assert_eq!(String::from("4"), calculation("--4").unwrap());
+        assert_eq!(String::from("-1"), calculation("(-3 + +2)").unwrap());
+    }
+
+    #[test]
+    fn calculation_test8() {
+        assert_eq!(String::from("512"), calculation("2 ^ 3 ^ 2").unwrap());
+        assert_eq!(String::from("-4"), calculation("-2 ^ 2").unwrap());
+        assert_eq!(String::from("4"), calculation("(-2) ** 2").unwrap());
+        assert_eq!(String::from("0.5"), calculation("2 ^ -1").unwrap());
+        assert_eq!(String::from("19"), calculation("1 + 2 * 3 ^ 2").unwrap());
+        assert_eq!(String::from("inf"), calculation("1 / 0").unwrap());
+        assert_eq!(String::from("inf"), calculation("0 ^ -1").unwrap());
+    }
+
+    #[test]
+    fn calculation_test9() {
+        assert_eq!(String::from("1"), calculation("7 % 3").unwrap());
+        assert_eq!(String::from("3"), calculation("7 // 2").unwrap());
+        assert_eq!(String::from("1.5"), calculation("5.5 % 2").unwrap());
+        assert_eq!(String::from("4"), calculation("1 + 10 // 3 * 1").unwrap());
+        assert_eq!(String::from("1"), calculation("-7 % 2").unwrap());
+        assert_eq!(String::from("-1"), calculation("7 % -2").unwrap());
+        assert_eq!(String::from("-4"), calculation("-7 // 2").unwrap());
+    }
+
+    #[test]
+    fn calculation_test10() {
+        assert_eq!(String::from("0.0015"), calculation("1.5e-3").unwrap());
+        assert_eq!(String::from("10000000000"), calculation("1e10").unwrap());
+        assert_eq!(String::from("3"), calculation("2E0 + 1e-0").unwrap());
+        assert_eq!(String::from("250"), calculation("2.5e2 * 1").unwrap());
+    }
+
+    #[test]
+    fn calculation_test11() {
+        assert_eq!(String::from("143"), calculation("0x80 + 0x0F").unwrap());
+        assert_eq!(String::from("8"), calculation("0B1000").unwrap());
+        assert_eq!(String::from("-511"), calculation("-0o777").unwrap());
+        assert_eq!(String::from("0"), calculation("0x0 * 10").unwrap());
+    }
+
+    #[test]
+    fn calculation_test12() {
+        assert_eq!(String::from("15"), calculation("１２＋３").unwrap());
+        assert_eq!(String::from("20"), calculation("４×５").unwrap());
+        assert_eq!(String::from("5"), calculation("１０÷２").unwrap());
+        assert_eq!(String::from("2"), calculation("（１　+　２）−１．０").unwrap());
+    }
+
+    #[test]
+    fn calculation_test13() {
+        assert_eq!(String::from("120"), calculation("5!").unwrap());
+        assert_eq!(String::from("-6"), calculation("-3!").unwrap());
+        assert_eq!(String::from("64"), calculation("2 ^ 3!").unwrap());
+        assert_eq!(String::from("6"), calculation("(2 + 1)!").unwrap());
+        assert_eq!(String::from("720"), calculation("(3!)!").unwrap());
+        assert_eq!(String::from("3"), calculation("3!!").unwrap());
+        assert_eq!(String::from("15"), calculation("5!! * 1").unwrap());
+        assert_eq!(String::from("10"), calculation("5! / (3! * 2!)").unwrap());
+    }
+
+    #[test]
+    fn calculation_test14() {
+        assert_eq!(String::from("9"), calculation("[1 + 2] * 3").unwrap());
+        assert_eq!(String::from("20"), calculation("{2 * [3 + (4 - 2)]} * 2").unwrap());
+        assert_eq!(String::from("9"), calculation("[[1 + 2] * 3]").unwrap());
+        assert_eq!(String::from("9"), calculation("{[1 + 2] * 3}").unwrap());
+        assert_eq!(String::from("4"), calculation("｛［１＋１］｝＊２").unwrap());
+    }
+
+    #[test]
+    fn calculation_test15() {
+        assert_eq!(String::from("true"), calculation("0.9 * 100 > 85").unwrap());
+        assert_eq!(String::from("true"), calculation("1 + 1 == 2").unwrap());
+        assert_eq!(String::from("false"), calculation("3 < 2").unwrap());
+        assert_eq!(String::from("false"), calculation("1 != 1").unwrap());
+        assert_eq!(String::from("true"), calculation("5! >= 120").unwrap());
+        assert_eq!(String::from("true"), calculation("0 <= 5 < 10").unwrap());
+        assert_eq!(String::from("false"), calculation("0 <= 15 < 10").unwrap());
+        assert_eq!(String::from("true"), calculation("(1 < 2) == (3 < 4)").unwrap());
+        assert_eq!(String::from("true"), calculation("3!==6").unwrap());
+        assert_eq!(String::from("true"), calculation("3!=7").unwrap());
+    }
+
+    #[test]
+    fn calculation_test16() {
+        assert_eq!(String::from("true"), calculation("1 < 2 and 2 < 3").unwrap());
+        assert_eq!(String::from("false"), calculation("1 < 2 && 3 < 2").unwrap());
+        assert_eq!(String::from("true"), calculation("1 > 2 or 2 < 3").unwrap());
+        assert_eq!(String::from("false"), calculation("1 > 2 || 3 < 2").unwrap());
+        assert_eq!(String::from("true"), calculation("not 1 > 2").unwrap());
+        assert_eq!(String::from("false"), calculation("!(1 < 2)").unwrap());
+        assert_eq!(String::from("true"), calculation("!!(1 < 2)").unwrap());
+        assert_eq!(String::from("true"), calculation("1 > 2 and 0 > 1 or 2 > 1").unwrap());
+        assert_eq!(String::from("false"), calculation("0 != 0 and 10 / 0 > 2").unwrap());
+        assert_eq!(String::from("true"), calculation("1 == 1 or 10 / 0 > 2").unwrap());
+    }
+
+    #[test]
+    fn calculation_test17() {
+        assert_eq!(
+            String::from("135"),
+            calculation("if 150 >= 100 then 150 * 0.9 else 150").unwrap()
+        );
+        assert_eq!(String::from("2"), calculation("if 50 >= 100 then 1 else 2").unwrap());
+        assert_eq!(String::from("10"), calculation("1 < 2 ? 10 : 20").unwrap());
+        assert_eq!(String::from("2"), calculation("1 > 2 ? 1 : 2 > 1 ? 2 : 3").unwrap());
+        assert_eq!(String::from("6"), calculation("(1 < 2 ? 3 : 4) * 2").unwrap());
+        assert_eq!(String::from("3"), calculation("1 + (if 1 > 2 then 1 else 2)").unwrap());
+        assert_eq!(String::from("1"), calculation("if 1 > 0 then 1 else 1 % 0").unwrap());
+        assert_eq!(String::from("5"), calculation("1 < 2 ? 5 : (-3)!").unwrap());
+    }
+
+    #[test]
+    fn calculation_test18() {
+        assert_eq!(String::from("143"), calculation("(1 << 7) | 0x0F").unwrap());
+        assert_eq!(String::from("4"), calculation("0b1100 & 0b0110").unwrap());
+        assert_eq!(String::from("10"), calculation("0b1100 xor 0b0110").unwrap());
+        assert_eq!(String::from("-6"), calculation("~5").unwrap());
+        assert_eq!(String::from("2"), calculation("16 >> 3").unwrap());
+        assert_eq!(String::from("-2"), calculation("-16 >> 3").unwrap());
+        assert_eq!(String::from("28"), calculation("1 + 2 << 3 + 1 - 1 | 4").unwrap());
+        assert_eq!(String::from("true"), calculation("6 & 3 == 2").unwrap());
+    }
+
+    #[test]
+    fn calculation_test19() {
+        assert_eq!(String::from("3.141592653589793"), calculation("pi").unwrap());
+        assert_eq!(String::from("2.718281828459045"), calculation("e").unwrap());
+        assert_eq!(String::from("true"), calculation("π * 2 == tau").unwrap());
+        assert_eq!(String::from("1.618033988749895"), calculation("φ").unwrap());
+        assert_eq!(String::from("true"), calculation("inf > 10 ^ 300").unwrap());
+        assert_eq!(String::from("false"), calculation("nan == nan").unwrap());
+    }
+
+    #[test]
+    fn calculation_test20() {
+        assert_eq!(String::from("5"), calculation("sqrt(3 ^ 2 + 4 ^ 2)").unwrap());
+        assert_eq!(String::from("8"), calculation("abs(-2) * 4").unwrap());
+        assert_eq!(String::from("-3"), calculation("-abs(floor(-2.5))").unwrap());
+        assert_eq!(String::from("true"), calculation("sin(pi / 2) == 1").unwrap());
+        assert_eq!(String::from("4"), calculation("round(log2(16.2))").unwrap());
+    }
+
+    #[test]
+    fn calculation_test21() {
+        assert_eq!(String::from("7"), calculation("max(3, 7, 2)").unwrap());
+        assert_eq!(String::from("2.5"), calculation("avg(1, 2, 3, 4)").unwrap());
+        assert_eq!(String::from("5"), calculation("hypot(3, 4)").unwrap());
+        assert_eq!(String::from("0"), calculation("sum()").unwrap());
+        assert_eq!(String::from("13"), calculation("sum(1, max(2, 3) * 4)").unwrap());
+    }
+
+    #[test]
+    fn calculation_test22() {
+        assert_eq!(String::from("3"), calculation("7 // 2  # 整数除算").unwrap());
+        assert_eq!(
+            String::from("1100"),
+            calculation("base = 1000 # 基準値\nbase * 1.1  # 10% margin").unwrap()
+        );
+        assert_eq!(String::from("5"), calculation("# 先頭のコメント\n5").unwrap());
+    }
+
+    #[test]
+    fn calculation_test23() {
+        assert_eq!(String::from("[11, 22, 33]"), calculation("[1, 2, 3] + [10, 20, 30]").unwrap());
+        assert_eq!(String::from("[2, 4, 6]"), calculation("[1, 2, 3] * 2").unwrap());
+        assert_eq!(String::from("[10, 5, 2.5]"), calculation("10 / [1, 2, 4]").unwrap());
+        assert_eq!(String::from("[-1, 2]"), calculation("-[1, -2]").unwrap());
+        assert_eq!(String::from("[2, 3]"), calculation("sqrt([4, 9])").unwrap());
+    }
+
+    // リストの記述、添字、リストを引数とする関数
+    #[test]
+    fn calculation_test24() {
+        assert_eq!(String::from("[]"), calculation("[]").unwrap());
+        assert_eq!(String::from("[5]"), calculation("[5,]").unwrap());
+        assert_eq!(String::from("[5]"), calculation("[5]").unwrap());
+        assert_eq!(String::from("[-5]"), calculation("[-5]").unwrap());
+        assert_eq!(String::from("6"), calculation("[2 * 3]").unwrap());
+        assert_eq!(String::from("1"), calculation("len([5])").unwrap());
+        assert_eq!(String::from("5"), calculation("xs = [5]; xs[0]").unwrap());
+        assert_eq!(String::from("[[1, 2], [3]]"), calculation("[[1, 2], [3]]").unwrap());
+        assert_eq!(String::from("[[1, 2, 3]]"), calculation("[[1, 2, 3]]").unwrap());
+        assert_eq!(String::from("[[1], [2]]"), calculation("[[1], [2]]").unwrap());
+        assert_eq!(String::from("[[5]]"), calculation("[[5]]").unwrap());
+        assert_eq!(String::from("2"), calculation("[1, 2, 3][1]").unwrap());
+        assert_eq!(String::from("3"), calculation("len([1, 2, 3])").unwrap());
+        assert_eq!(String::from("6"), calculation("sum([1, 2], 3)").unwrap());
+        assert_eq!(String::from("4"), calculation("max([1, 4, 2])").unwrap());
+    }
+
+    // 行列の積、転置
+    #[test]
+    fn calculation_test25() {
+        assert_eq!(
+            String::from("[[19, 22], [43, 50]]"),
+            calculation("[[1, 2], [3, 4]] @ [[5, 6], [7, 8]]").unwrap()
+        );
+        assert_eq!(
+            String::from("[[5, 12], [21, 32]]"),
+            calculation("[[1, 2], [3, 4]] * [[5, 6], [7, 8]]").unwrap()
+        );
+        assert_eq!(String::from("[5, 11]"), calculation("[[1, 2], [3, 4]] @ [1, 2]").unwrap());
+        assert_eq!(String::from("[7, 10]"), calculation("[1, 2] @ [[1, 2], [3, 4]]").unwrap());
+        assert_eq!(String::from("32"), calculation("[1, 2, 3] @ [4, 5, 6]").unwrap());
+        assert_eq!(
+            String::from("[[1, 4], [2, 5], [3, 6]]"),
+            calculation("transpose([[1, 2, 3], [4, 5, 6]])").unwrap()
+        );
+        assert_eq!(
+            String::from("[[1], [2], [3]]"), calculation("transpose([[1, 2, 3]])").unwrap()
+        );
+        assert_eq!(
+            String::from("[[14]]"), calculation("[[1, 2, 3]] @ [[1], [2], [3]]").unwrap()
+        );
+    }
+
+    // 行列式、逆行列、連立方程式
+    #[test]
+    fn calculation_test26() {
+        assert_eq!(String::from("1"), calculation("det([[2, 1], [1, 1]])").unwrap());
+        assert_eq!(String::from("0"), calculation("det([[1, 2], [2, 4]])").unwrap());
+        assert_eq!(String::from("5"), calculation("det([[5]])").unwrap());
+        let det = evaluate("det([[2, -3, 1], [2, 0, -1], [1, 4, 5]])").unwrap();
+        assert!((det.as_number().unwrap() - 49.0).abs() < 1e-9);
+
+        assert_eq!(
+            String::from("[[1, -1], [-1, 2]]"), calculation("inv([[2, 1], [1, 1]])").unwrap()
+        );
+        assert_eq!(String::from("[1, 1]"), calculation("solve([[2, 1], [1, 1]], [3, 2])").unwrap());
+        assert_eq!(
+            String::from("[[1], [1]]"), calculation("solve([[2, 1], [1, 1]], [[3], [2]])").unwrap()
+        );
+
+        // 正則かどうかは要素の大きさによらず判定する
+        assert_eq!(
+            String::from("[[10000000000000, 0], [0, 10000000000000]]"),
+            calculation("inv([[1e-13, 0], [0, 1e-13]])").unwrap()
+        );
+        assert_eq!(
+            String::from("[10000000000000, 5000000000000]"),
+            calculation("solve([[1e-13, 0], [0, 2e-13]], [1, 1])").unwrap()
+        );
+        assert_eq!(
+            String::from("[[0.0000000000001, 0], [0, 0.00000000000005]]"),
+            calculation("inv([[1e13, 0], [0, 2e13]])").unwrap()
+        );
+        assert_eq!(String::from("0"), calculation("det([[1, 2, 3], [4, 5, 6], [7, 8, 9]])").unwrap());
+        assert_eq!(String::from("0"), calculation("det([[1e20, 2e20], [2e20, 4e20]])").unwrap());
+
+        let mut calculator = Calculator::new();
+        calculator.calculation("a = [[4, 7], [2, 6]]; b = [1, 2]; x = solve(a, b)").unwrap();
+        let check = calculator.evaluate("a @ x - b").unwrap();
+        assert!(to_numbers(&[check]).unwrap().iter().all(|value| value.abs() < 1e-9));
+    }
+
+    // 複素数の計算
+    #[test]
+    fn calculation_test27() {
+        assert_eq!(String::from("2i"), calculation("sqrt(-4)").unwrap());
+        assert_eq!(String::from("-1"), calculation("i * i").unwrap());
+        assert_eq!(String::from("true"), calculation("i^2 == -1").unwrap());
+        assert_eq!(String::from("5+5i"), calculation("(1 + 2i) * (3 - i)").unwrap());
+        assert_eq!(String::from("-1+2i"), calculation("(3 + 4j) / (1 - 2j)").unwrap());
+        assert_eq!(String::from("-2i"), calculation("-2j").unwrap());
+        assert_eq!(String::from("1-1i"), calculation("sqrt(-2i)").unwrap());
+        assert_eq!(String::from("[1i, 2i]"), calculation("[1, 2] * i").unwrap());
+
+        // 0での除算、0のべき乗は実数と同じく inf・NaN とする
+        assert_eq!(String::from("infi"), calculation("i / 0").unwrap());
+        assert_eq!(String::from("inf+infi"), calculation("(1 + 2i) / 0").unwrap());
+        assert_eq!(String::from("inf"), calculation("0 ^ (-1 + i)").unwrap());
+        assert_eq!(String::from("0"), calculation("0 ^ (1 + i)").unwrap());
+        assert_eq!(String::from("NaN"), calculation("0 ^ i").unwrap());
+    }
+
+    // 複素数の関数
+    #[test]
+    fn calculation_test28() {
+        assert_eq!(String::from("5"), calculation("abs(3 + 4i)").unwrap());
+        assert_eq!(String::from("3"), calculation("re(3 + 4i)").unwrap());
+        assert_eq!(String::from("4"), calculation("im(3 + 4i)").unwrap());
+        assert_eq!(String::from("1.5707963267948966"), calculation("arg(i)").unwrap());
+        assert_eq!(String::from("3-4i"), calculation("conj(3 + 4i)").unwrap());
+        assert_eq!(String::from("2"), calculation("polar(2, 0)").unwrap());
+        let (re, im) = to_complex(&evaluate("polar(2, pi / 2)").unwrap()).unwrap();
+        assert!(re.abs() < 1e-12 && (im - 2.0).abs() < 1e-12);
+        assert_eq!(String::from("[3, 0]"), calculation("abs([-3, 0])").unwrap());
+    }
+
+    #[test]
+    fn evaluate_test1() {
+        assert_eq!(Answer::Number(7.0), evaluate("1 + 2 * 3").unwrap());
+        assert_eq!(Answer::Bool(true), evaluate("1 + 2 * 3 <= 7").unwrap());
+        assert!(evaluate("2 > 1").unwrap().as_bool().unwrap());
+    }
+
+    //----- Calculator構造体 test ----------------------------------------------
+    #[test]
+    fn calculator_test1() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(String::from("0.08"), calculator.calculation("rate = 0.08").unwrap());
+        assert_eq!(Some(&Answer::Number(0.08)), calculator.get_variable("rate"));
+
+        calculator.set_variable("price", 1000.0);
+        assert_eq!(String::from("1080"), calculator.calculation("price * (1 + rate)").unwrap());
+    }
+
+    // 連続した代入、真偽値の代入、再代入
+    #[test]
+    fn calculator_test2() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(String::from("3"), calculator.calculation("a = b = 1 + 2").unwrap());
+        assert_eq!(Some(&Answer::Number(3.0)), calculator.get_variable("b"));
+
+        calculator.calculation("flag = a > 2").unwrap();
+        assert_eq!(Some(&Answer::Bool(true)), calculator.get_variable("flag"));
+
+        assert_eq!(String::from("4"), calculator.calculation("a = a + 1").unwrap());
+        assert_eq!(String::from("7"), calculator.calculation("a + b").unwrap());
+
+        assert_eq!(Some(Answer::Number(4.0)), calculator.remove_variable("a"));
+        assert_eq!(2, calculator.variables().len());
+    }
+
+    #[test]
+    fn calculator_test3() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            vec![Answer::Number(3.0), Answer::Number(4.0), Answer::Number(5.0)],
+            calculator.evaluate_all("a = 3; b = 4; sqrt(a^2 + b^2)").unwrap()
+        );
+        assert_eq!(String::from("7"), calculator.calculation("a + b").unwrap());
+        assert_eq!(String::from("6"), calculation("x = 2\ny = x * 3\n").unwrap());
+    }
+
+    // 関数の定義と呼び出し
+    #[test]
+    fn calculator_test4() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(String::from("f(x, y)"), calculator.calculation("f(x, y) = x^2 + y^2").unwrap());
+        assert_eq!(String::from("25"), calculator.calculation("f(3, 4)").unwrap());
+        assert_eq!(String::from("26"), calculator.calculation("f(f(1, 0), 5)").unwrap());
+
+        assert_eq!(String::from("one()"), calculator.calculation("one() = 1").unwrap());
+        assert_eq!(String::from("2"), calculator.calculation("one() + one()").unwrap());
+
+        assert!(calculator.remove_function("one"));
+        assert!(!calculator.has_function("one"));
+        assert!(calculator.has_function("f"));
+    }
+
+    // 再帰呼び出し、引数による変数・定数の隠蔽
+    #[test]
+    fn calculator_test5() {
+        let mut calculator = Calculator::new();
+
+        calculator.calculation("fact(n) = if n <= 1 then 1 else n * fact(n - 1)").unwrap();
+        assert_eq!(String::from("3628800"), calculator.calculation("fact(10)").unwrap());
+
+        calculator.calculation("x = 10; double(x) = x * 2; add(y) = x + y").unwrap();
+        assert_eq!(String::from("6"), calculator.calculation("double(3)").unwrap());
+        assert_eq!(String::from("13"), calculator.calculation("add(3)").unwrap());
+        assert_eq!(String::from("10"), calculator.calculation("x").unwrap());
+
+        calculator.calculation("g(e) = e + 1").unwrap();
+        assert_eq!(String::from("2"), calculator.calculation("g(1)").unwrap());
+    }
+
+    // 再帰呼び出しの上限
+    #[test]
+    fn calculator_test6() {
+        let options = Options {
+            max_call_depth: 5,
+            ..Options::default()
+        };
+        let mut calculator = Calculator::with_options(options);
+
+        calculator.calculation("fact(n) = if n <= 1 then 1 else n * fact(n - 1)").unwrap();
+        assert_eq!(String::from("120"), calculator.calculation("fact(5)").unwrap());
+
+        match calculator.calculation("fact(6)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("関数「fact」の呼び出しが上限の5回を超えました。", value),
+        }
+
+        // 計算に失敗した場合も引数は残らない
+        match calculator.calculation("n") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("定義されていない変数です。「n」", value),
+        }
+    }
+
+    #[test]
+    fn calculator_test7() {
+        let mut calculator = Calculator::new();
+        calculator.set_variable("xs", vec![10.0, 20.0, 40.0]);
+
+        assert_eq!(String::from("[11, 22, 44]"), calculator.calculation("xs * 1.1").unwrap());
+        assert_eq!(String::from("40"), calculator.calculation("xs[len(xs) - 1]").unwrap());
+        assert_eq!(
+            String::from("[1, 2, 4]"),
+            calculator.calculation("normalize(v) = v / v[0]; normalize(xs)").unwrap()
+        );
+    }
+
+    // 虚数単位と同じ名前の変数、インピーダンスの計算
+    #[test]
+    fn calculator_test8() {
+        let mut calculator = Calculator::new();
+
+        calculator.calculation("r = 30; x = 40; z = r + x * j").unwrap();
+        assert_eq!(Some(&Answer::Complex(30.0, 40.0)), calculator.get_variable("z"));
+        assert_eq!(String::from("50"), calculator.calculation("abs(z)").unwrap());
+        assert_eq!(String::from("1.2-1.6i"), calculator.calculation("100 / z").unwrap());
+
+        assert_eq!(String::from("10"), calculator.calculation("i = 5; i * 2").unwrap());
+        assert_eq!(String::from("-1"), calculator.calculation("j * j").unwrap());
+    }
+
+    // 関数を定義した状態でも別スレッドに渡せること
+    #[test]
+    fn calculator_test9() {
+        let mut calculator = Calculator::new();
+        calculator.calculation("f(x) = x * 2").unwrap();
+
+        let handle = std::thread::spawn(move || calculator.calculation("f(21)"));
+        assert_eq!(String::from("42"), handle.join().unwrap().unwrap());
+    }
+
+    #[test]
+    fn calculator_err_test1() {
+        let mut calculator = Calculator::new();
+
+        match calculator.calculation("price * 2") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("定義されていない変数です。「price」", value),
+        }
+
+        match calculator.calculation("pi = 3") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("定数に代入することはできません。「pi」", value),
+        }
+
+        // 計算に失敗した場合は代入しない
+        match calculator.calculation("x = 1 % 0") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("0で除算することはできません。", value),
+        }
+        assert_eq!(None, calculator.get_variable("x"));
+    }
+
+    #[test]
+    fn calculator_err_test2() {
+        let mut calculator = Calculator::new();
+        calculator.calculation("f(x, y) = x + y; loop(n) = loop(n + 1)").unwrap();
+
+        match calculator.calculation("f(1)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("関数「f」の引数は2個です。（1個指定されました）", value),
+        }
+
+        match calculator.calculation("loop(0)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("関数「loop」の呼び出しが上限の100回を超えました。", value),
+        }
+
+        match calculator.calculation("sqrt(x) = x") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("組み込み関数は定義し直すことはできません。「sqrt」", value),
+        }
+
+        match calculator.calculation("h(x, y, x) = x") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("関数「h」の引数名が重複しています。「x」", value),
+        }
+
+        match calculator.calculation("f(1) = 2") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "「=」の左辺には変数名、または関数名と引数名を指定してください。", value
+            ),
+        }
+
+        match calculator.calculation("f + 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("定義されていない変数です。「f」", value),
+        }
+    }
+
+    #[test]
+    fn calculation_with_options_test1() {
+        let options = Options {
+            division: Division::Truncated,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("-1"), calculation_with_options("-7 % 2", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("1"), calculation_with_options("7 % -2", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("-3"), calculation_with_options("-7 // 2", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculation_with_options_test2() {
+        let options = Options {
+            implicit_multiplication: ImplicitMultiplication::SamePrecedence,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("14"), calculation_with_options("2(3 + 4)", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("21"), calculation_with_options("(1 + 2)(3 + 4)", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("1.5"), calculation_with_options("1 / 2(3)", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("18"), calculation_with_options("2(3)^2", &options).unwrap()
+        );
+
+        // 数値・かっこの後の「[」は添字ではなく乗算として扱う
+        assert_eq!(
+            String::from("14"), calculation_with_options("2[3 + 4]", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("21"), calculation_with_options("(1 + 2)[3 + 4]", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("20"), calculation_with_options("xs = [10, 20]; xs[1]", &options).unwrap()
+        );
+
+        // 変数の後の「[」は値によらず添字として扱い、選択した優先順位を変えない
+        assert_eq!(
+            String::from("16"), calculation_with_options("x = [1, 2, 3, 4, 5]; x[3]^2", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("0.2"), calculation_with_options("x = [1, 2, 3, 4, 5]; 1 / x[4]", &options).unwrap()
+        );
+        match calculation_with_options("x = 2; x[3]^2", &options) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("リストを期待していましたが、数値が出現しました。「2」", value),
+        }
+        match calculation_with_options("x = 2; 1 / x[4]", &options) {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("リストを期待していましたが、数値が出現しました。「2」", value),
+        }
+    }
+
+    #[test]
+    fn calculation_with_options_test3() {
+        let options = Options {
+            implicit_multiplication: ImplicitMultiplication::HigherPrecedence,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("0.25"), calculation_with_options("1 / 2(2)", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("-6"), calculation_with_options("-2(1 + 2)", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("3"), calculation_with_options("12 / (1 + 1)(2)", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculation_with_options_test4() {
+        let options = Options {
+            gamma_factorial: true,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("24"), calculation_with_options("4!", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("3.323350970447843"),
+            calculation_with_options("2.5!", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculation_with_options_test5() {
+        let options = Options {
+            percent_sign: PercentSign::Percent,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("0.5"), calculation_with_options("50%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("220"), calculation_with_options("200 + 10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("180"), calculation_with_options("200 - 10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("20"), calculation_with_options("200 * 10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("2000"), calculation_with_options("200 / 10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("180"), calculation_with_options("200 + -10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("220"), calculation_with_options("200 - -10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("[110, 220]"),
+            calculation_with_options("[100, 200] + 10%", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("242"), calculation_with_options("200 + 10% + 10%", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculation_with_options_test6() {
+        let mut options = Options {
+            implicit_multiplication: ImplicitMultiplication::SamePrecedence,
+            ..Options::default()
+        };
+        options.constants.insert(String::from("g"), 9.80665);
+
+        assert_eq!(
+            String::from("19.6133"), calculation_with_options("2 * g", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("6.283185307179586"), calculation_with_options("2pi", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("19.6133"), calculation_with_options("(1 + 1)g", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculation_with_options_test7() {
+        let options = Options {
+            double_slash: DoubleSlash::Comment,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("1100"),
+            calculation_with_options("1000 * 1.1 // 10% margin", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("3.5"),
+            calculation_with_options("// 除算\n7 / 2 # 結果", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn calculation_with_options_test8() {
+        let options = Options {
+            complex_format: ComplexFormat::Polar,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            String::from("5∠0.9272952180016122"),
+            calculation_with_options("3 + 4i", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("[1, 2∠-1.5707963267948966]"),
+            calculation_with_options("[1, -2i]", &options).unwrap()
+        );
+        assert_eq!(
+            String::from("3+4i"), evaluate_with_options("3 + 4i", &options).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn calculation_error_test1() {
+        match calculation("2.5 + 3..5") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("「.」が連続して出現しました。", value),
+        }
+    }
+
+    #[test]
+    fn calculation_error_test2() {
+        match calculation("ろ + 3..5") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("予期せぬ文字が出現しました。「ろ」", value),
+        }
+    }
 
-        assert_eq!("2", block.execute().unwrap().as_str());
+    #[test]
+    fn calculation_error_test3() {
+        match calculation("2 * / 3") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、数値以外が出現しました。", value
+            ),
+        }
     }
 
-    // 4 * 4 - 6
     #[test]
-    fn block_execute_inner_block_left_test() {
-        let block = Block::new(
-            Value::Val(String::from("4")),
-            Value::Val(String::from("4")),
-            Value::Op(Operator::Multiply),
-        );
+    fn calculation_error_test4() {
+        match calculation("7 // (2 - 2)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("0で除算することはできません。", value),
+        }
+    }
 
-        let block = Block::new(
-            Value::Block(Box::new(block)),
-            Value::Val(String::from("6")),
-            Value::Op(Operator::Minus),
-        );
+    #[test]
+    fn calculation_error_test5() {
+        match calculation("5 % (3 - 3)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("0で除算することはできません。", value),
+        }
+    }
+
+    #[test]
+    fn calculation_error_test6() {
+        match calculation("(-3)!") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("負の整数の階乗は計算できません。「-3」", value),
+        }
+
+        match calculation("2.5!") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("整数以外の階乗は計算できません。「2.5」", value),
+        }
 
-        assert_eq!("10", block.execute().unwrap().as_str());
+        match calculation("2.5!!") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("整数以外の二重階乗は計算できません。「2.5」", value),
+        }
     }
 
-    // (5 + 5) / 2
     #[test]
-    fn block_execute_inner_block_right_test() {
-        let block = Block::new(
-            Value::Val(String::from("5")),
-            Value::Val(String::from("5")),
-            Value::Op(Operator::Plus),
-        );
+    fn calculation_error_test7() {
+        match calculation("{2 * [3 + (4 - 2)}]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "かっこの種類が一致しません。「[」（6文字目）と「}」（18文字目）", value
+            ),
+        }
+    }
 
-        let block = Block::new(
-            Value::Val(String::from("2")),
-            Value::Block(Box::new(block)),
-            Value::Op(Operator::Divide),
-        );
+    #[test]
+    fn calculation_error_test8() {
+        match calculation("(1 < 2) + 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、真偽値が出現しました。「true」", value
+            ),
+        }
 
-        assert_eq!("0.2", block.execute().unwrap().as_str());
+        match calculation("1 = 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "「=」の左辺には変数名、または関数名と引数名を指定してください。", value
+            ),
+        }
     }
 
-    // (3 + 7) * (6 + 4)
     #[test]
-    fn block_execute_inner_block_left_right_test() {
-        let block_l = Block::new(
-            Value::Val(String::from("3")),
-            Value::Val(String::from("7")),
-            Value::Op(Operator::Plus),
-        );
+    fn calculation_error_test9() {
+        match calculation("1 and 2 > 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "真偽値を期待していましたが、数値が出現しました。「1」", value
+            ),
+        }
 
-        let block_r = Block::new(
-            Value::Val(String::from("6")),
-            Value::Val(String::from("4")),
-            Value::Op(Operator::Plus),
-        );
+        match calculation("(1 < 2) & 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、真偽値が出現しました。「true」", value
+            ),
+        }
 
-        let block = Block::new(
-            Value::Block(Box::new(block_l)),
-            Value::Block(Box::new(block_r)),
-            Value::Op(Operator::Multiply),
-        );
+        match calculation("1 xyz 2") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "演算子を期待していましたが、演算子以外が出現しました。", value
+            ),
+        }
+    }
+
+    #[test]
+    fn calculation_error_test10() {
+        match calculation("if 1 < 2 then 3") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "「else」を期待していましたが、「else」以外が出現しました。", value
+            ),
+        }
+
+        match calculation("1 < 2 ? 3") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "「:」を期待していましたが、「:」以外が出現しました。", value
+            ),
+        }
 
-        assert_eq!("100", block.execute().unwrap().as_str());
+        match calculation("if 1 then 2 else 3") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "真偽値を期待していましたが、数値が出現しました。「1」", value
+            ),
+        }
     }
 
-    // 24 / (2 * 2 + 4)
     #[test]
-    fn block_execute_inner_block_double_test() {
-        let block = Block::new(
-            Value::Val(String::from("2")),
-            Value::Val(String::from("2")),
-            Value::Op(Operator::Multiply),
-        );
+    fn calculation_error_test11() {
+        match calculation("1.5 | 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("整数以外にビット演算は使用できません。「1.5」", value),
+        }
 
-        let block = Block::new(
-            Value::Block(Box::new(block)),
-            Value::Val(String::from("4")),
-            Value::Op(Operator::Plus),
-        );
+        match calculation("1 << 64") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("シフト量は0以上64未満で指定してください。「64」", value),
+        }
 
-        let block = Block::new(
-            Value::Val(String::from("24")),
-            Value::Block(Box::new(block)),
-            Value::Op(Operator::Divide),
-        );
+        match calculation("1 >> -1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("シフト量は0以上64未満で指定してください。「-1」", value),
+        }
+    }
 
-        assert_eq!("3", block.execute().unwrap().as_str());
+    #[test]
+    fn calculation_error_test12() {
+        match calculation("x + 1") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("定義されていない変数です。「x」", value),
+        }
     }
 
-    //----- calculation test ---------------------------------------------------
     #[test]
-    fn calculation_test1() {
-        let result = calculation("10 * 2 + 1").unwrap();
-        assert_eq!(String::from("21"), result);
+    fn calculation_error_test13() {
+        match calculation("sqrt(1, 2)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "関数「sqrt」の引数は1個です。（2個指定されました）", value
+            ),
+        }
+
+        match calculation("log(8)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "関数「log」の引数は2個です。（1個指定されました）", value
+            ),
+        }
+
+        match calculation("foo(1)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("定義されていない関数です。「foo」", value),
+        }
+
+        match calculation("sqrt(4") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("終了かっこが不足しています。", value),
+        }
     }
 
     #[test]
-    fn calculation_test2() {
-        let result = calculation("10 * 2 + 1 - 2 + 10 / 2").unwrap();
-        assert_eq!(String::from("24"), result);
+    fn calculation_error_test14() {
+        match calculation("max()") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "関数「max」の引数は1個以上必要です。（0個指定されました）", value
+            ),
+        }
+
+        match calculation("avg(1, 2,)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("想定外の終了かっこが出現しました。", value),
+        }
     }
 
     #[test]
-    fn calculation_test3() {
-        let result = calculation("(2.5 + 1.3) * 2").unwrap();
-        assert_eq!(String::from("7.6"), result);
+    fn calculation_error_test15() {
+        match calculation(" ; \n ") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("数値の取得に失敗しました。", value),
+        }
+
+        match calculation("1 + ; 2") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、数値以外が出現しました。", value
+            ),
+        }
     }
 
+    // コメントを読み飛ばしてもエラーの位置は入力文字列上の位置となる
     #[test]
-    fn calculation_test4() {
-        let result = calculation("(3 - 4 * 2) / (9 / 3 - 1)").unwrap();
-        assert_eq!(String::from("-2.5"), result);
+    fn calculation_error_test16() {
+        match calculation("1 # (\n(2 + 3]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "かっこの種類が一致しません。「(」（7文字目）と「]」（13文字目）", value
+            ),
+        }
+
+        match calculation("# コメントのみ") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("数値の取得に失敗しました。", value),
+        }
     }
 
     #[test]
-    fn calculation_test5() {
-        let result = calculation("((2 * (1 + 1)) + 3) / 2").unwrap();
-        assert_eq!(String::from("3.5"), result);
+    fn calculation_error_test17() {
+        match calculation("[1, 2, 3] + [1, 2]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("リストの大きさが一致しません。（3 と 2）", value),
+        }
+
+        match calculation("[5] + [1, 2]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("リストの大きさが一致しません。（1 と 2）", value),
+        }
+
+        match calculation("[1, 2, 3][3]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("添字が範囲外です。「3」（要素数3個）", value),
+        }
+
+        match calculation("[1, 2, 3][0.5]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("添字には整数を指定してください。「0.5」", value),
+        }
+
+        match calculation("len(5)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "リストを期待していましたが、数値が出現しました。「5」", value
+            ),
+        }
+
+        match calculation("[1, 2] < 3") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、リストが出現しました。「[1, 2]」", value
+            ),
+        }
+
+        match calculation("max([])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "関数「max」の引数は1個以上必要です。（0個指定されました）", value
+            ),
+        }
     }
 
+    // 行列の大きさが一致しない場合は両方の大きさをエラーに含める
     #[test]
-    fn calculation_error_test1() {
-        match calculation("2.5 + 3..5") {
+    fn calculation_error_test18() {
+        match calculation("[[1, 2, 3], [4, 5, 6]] @ [[1, 2], [3, 4]]") {
             Ok(_) => panic!("エラーが発生しませんでした。"),
-            Err(value) => assert_eq!("「.」が連続して出現しました。", value),
+            Err(value) => assert_eq!("行列の大きさが一致しません。（2x3 と 2x2）", value),
+        }
+
+        match calculation("[[1, 2], [3, 4]] + [[1, 2, 3], [4, 5, 6]]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("リストの大きさが一致しません。（2x2 と 2x3）", value),
+        }
+
+        match calculation("[[1, 2], [3, 4]] @ [[1, 2, 3]]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列の大きさが一致しません。（2x2 と 1x3）", value),
+        }
+
+        match calculation("solve([[1, 2], [3, 4]], [1, 2, 3])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列の大きさが一致しません。（2x2 と 3）", value),
+        }
+
+        match calculation("det([[1, 2, 3], [4, 5, 6]])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "関数「det」の引数は正方行列である必要があります。（2x3）", value
+            ),
+        }
+
+        match calculation("inv([[1, 2], [2, 4]])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列が正則ではないため計算できません。", value),
+        }
+
+        // det が 0 となる行列は、要素の大きさによらず inv でもエラーとする
+        match calculation("inv([[1, 2, 3], [4, 5, 6], [7, 8, 9]])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列が正則ではないため計算できません。", value),
+        }
+
+        match calculation("inv([[1e20, 2e20], [2e20, 4e20]])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列が正則ではないため計算できません。", value),
+        }
+
+        match calculation("inv([[1e-20, 2e-20], [2e-20, 4e-20]])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列が正則ではないため計算できません。", value),
+        }
+
+        match calculation("transpose([[1, 2], [3]])") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "行列の各行の要素数が一致しません。「[[1, 2], [3]]」", value
+            ),
+        }
+
+        match calculation("2 @ [1, 2]") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "リストを期待していましたが、数値が出現しました。「2」", value
+            ),
+        }
+
+        match calculation("[] @ []") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列の要素がありません。", value),
+        }
+
+        match calculation("[[1, 2], [3, 4]] @ []") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("行列の要素がありません。", value),
         }
     }
 
     #[test]
-    fn calculation_error_test2() {
-        match calculation("ろ + 3..5") {
+    fn calculation_error_test19() {
+        match calculation("1i < 2") {
             Ok(_) => panic!("エラーが発生しませんでした。"),
-            Err(value) => assert_eq!("予期せぬ文字が出現しました。「ろ」", value),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、複素数が出現しました。「1i」", value
+            ),
+        }
+
+        match calculation("sin(1 + i)") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!(
+                "数値を期待していましたが、複素数が出現しました。「1+1i」", value
+            ),
+        }
+
+        match calculation("2i % 2") {
+            Ok(_) => panic!("エラーが発生しませんでした。"),
+            Err(value) => assert_eq!("複素数には使用できない演算子です。", value),
         }
     }
 }
\ No newline at end of file